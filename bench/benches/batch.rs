@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, SamplingMode};
+
+use crawdad::Trie;
+
+const SAMPLE_SIZE: usize = 10;
+const WARM_UP_TIME: Duration = Duration::from_secs(5);
+const MEASURE_TIME: Duration = Duration::from_secs(10);
+
+// Generates distinct keys of kana characters with a xorshift generator,
+// so that the dictionary does not fit in the cache.
+fn make_keys(num_keys: usize) -> Vec<String> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut keys = Vec::with_capacity(num_keys);
+    for i in 0..num_keys {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let len = 2 + (state % 8) as usize;
+        let mut key: String = (0..len)
+            .map(|j| char::from_u32(0x30a1 + ((state >> (j * 6)) % 86) as u32).unwrap())
+            .collect();
+        key.push_str(&i.to_string());
+        keys.push(key);
+    }
+    keys
+}
+
+fn criterion_exact_match(c: &mut Criterion) {
+    let mut group = c.benchmark_group("exact_match");
+    group.sampling_mode(SamplingMode::Flat);
+    group.sample_size(SAMPLE_SIZE);
+    group.warm_up_time(WARM_UP_TIME);
+    group.measurement_time(MEASURE_TIME);
+
+    let keys = make_keys(1_000_000);
+    let trie = Trie::from_keys(&keys).unwrap();
+
+    // Shuffles the queries to avoid sequential accesses.
+    let mut queries = keys.clone();
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    for i in (1..queries.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        queries.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+
+    group.bench_function("loop", |b| {
+        b.iter(|| {
+            for query in &queries {
+                black_box(trie.exact_match(query.chars()));
+            }
+        });
+    });
+
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            black_box(trie.exact_match_batch(&queries));
+        });
+    });
+}
+
+criterion_group!(benches, criterion_exact_match);
+criterion_main!(benches);
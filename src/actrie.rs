@@ -0,0 +1,832 @@
+//! An Aho-Corasick automaton form that provides linear-time pattern matching.
+use crate::builder::Builder;
+use crate::errors::Result;
+use crate::mapper::CodeMapper;
+use crate::normalizer;
+use crate::sections::Sections;
+use crate::{Match, MatchKind, Node};
+
+use crate::{END_CODE, INVALID_IDX};
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use core::mem;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+struct Link {
+    fail: u32,
+    output: u32,
+    depth: u32,
+}
+
+impl Link {
+    const fn io_bytes() -> usize {
+        12
+    }
+
+    #[inline(always)]
+    fn serialize(&self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes[0..4].copy_from_slice(&self.fail.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.output.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.depth.to_le_bytes());
+        bytes
+    }
+
+    #[inline(always)]
+    fn deserialize(bytes: [u8; 12]) -> Self {
+        Self {
+            fail: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            output: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            depth: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+}
+
+/// An Aho-Corasick automaton form that provides linear-time pattern matching.
+///
+/// It augments nodes of the character-wise double array of [`Trie`](crate::Trie)
+/// with failure and output links, so that occurrences of keys in a haystack are found
+/// by scanning the haystack only once.
+pub struct AcTrie {
+    pub(crate) mapper: CodeMapper,
+    pub(crate) nodes: Vec<Node>,
+    links: Vec<Link>,
+    // The maximum length of keys in characters, which is derived from `links`.
+    max_depth: u32,
+}
+
+impl AcTrie {
+    /// Creates a new [`AcTrie`] from input keys.
+    ///
+    /// Values in `[0..n-1]` will be associated with keys in the lexicographical order,
+    /// where `n` is the number of keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: Sorted list of string keys.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - `keys` is empty,
+    /// - `keys` contains empty strings,
+    /// - `keys` contains duplicate keys,
+    /// - the scale of `keys` exceeds the expected one, or
+    /// - the scale of the resulting trie exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::AcTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = AcTrie::from_keys(keys).unwrap();
+    ///
+    /// assert_eq!(trie.num_elems(), 8);
+    /// ```
+    pub fn from_keys<I, K>(keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        Builder::new().build_from_keys(keys)?.release_actrie()
+    }
+
+    /// Creates a new [`AcTrie`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: Sorted list of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - `records` is empty,
+    /// - `records` contains empty strings,
+    /// - `records` contains duplicate keys,
+    /// - the scale of `keys` exceeds the expected one, or
+    /// - the scale of the resulting trie exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::AcTrie;
+    ///
+    /// let records = vec![("世界", 2), ("世界中", 3), ("国民", 2)];
+    /// let trie = AcTrie::from_records(records).unwrap();
+    ///
+    /// assert_eq!(trie.num_elems(), 8);
+    /// ```
+    pub fn from_records<I, K>(records: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, u32)>,
+        K: AsRef<str>,
+    {
+        Builder::new().build_from_records(records)?.release_actrie()
+    }
+
+    /// Builds failure and output links on the nodes of a trie.
+    pub(crate) fn new(mapper: CodeMapper, nodes: Vec<Node>) -> Self {
+        let mut trie = Self {
+            mapper,
+            nodes,
+            links: vec![],
+            max_depth: 0,
+        };
+        let num_nodes = trie.nodes.len();
+
+        // Lists the children of each state in the CSR format using the parent links.
+        let mut offsets = vec![0u32; num_nodes + 1];
+        for node_idx in 1..trie.num_nodes() {
+            if trie.is_state(node_idx) {
+                offsets[usize::try_from(trie.get_check(node_idx)).unwrap() + 1] += 1;
+            }
+        }
+        for i in 0..num_nodes {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut children = vec![0u32; usize::try_from(offsets[num_nodes]).unwrap()];
+        let mut heads = offsets.clone();
+        for node_idx in 1..trie.num_nodes() {
+            if trie.is_state(node_idx) {
+                let parent_idx = usize::try_from(trie.get_check(node_idx)).unwrap();
+                children[usize::try_from(heads[parent_idx]).unwrap()] = node_idx;
+                heads[parent_idx] += 1;
+            }
+        }
+
+        // Defines the links in the breadth-first order.
+        let mut links = vec![
+            Link {
+                fail: 0,
+                output: INVALID_IDX,
+                depth: 0,
+            };
+            num_nodes
+        ];
+        let mut queue = VecDeque::new();
+        queue.push_back(0);
+        while let Some(node_idx) = queue.pop_front() {
+            let node_pos = usize::try_from(node_idx).unwrap();
+            let beg = usize::try_from(offsets[node_pos]).unwrap();
+            let end = usize::try_from(offsets[node_pos + 1]).unwrap();
+            for &child_idx in &children[beg..end] {
+                let mc = trie.get_base(node_idx) ^ child_idx;
+                let fail_idx = if node_idx == 0 {
+                    0
+                } else {
+                    let mut state_idx = links[node_pos].fail;
+                    loop {
+                        if let Some(next_idx) = trie.get_child_idx(state_idx, mc) {
+                            break next_idx;
+                        }
+                        if state_idx == 0 {
+                            break 0;
+                        }
+                        state_idx = links[usize::try_from(state_idx).unwrap()].fail;
+                    }
+                };
+                let fail_pos = usize::try_from(fail_idx).unwrap();
+                let output = if trie.get_state_value(fail_idx).is_some() {
+                    fail_idx
+                } else {
+                    links[fail_pos].output
+                };
+                let depth = links[node_pos].depth + 1;
+                links[usize::try_from(child_idx).unwrap()] = Link {
+                    fail: fail_idx,
+                    output,
+                    depth,
+                };
+                trie.max_depth = trie.max_depth.max(depth);
+                queue.push_back(child_idx);
+            }
+        }
+        trie.links = links;
+        trie
+    }
+
+    /// Serializes the data structure into a [`Vec`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::AcTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = AcTrie::from_keys(&keys).unwrap();
+    /// let bytes = trie.serialize_to_vec();
+    /// ```
+    pub fn serialize_to_vec(&self) -> Vec<u8> {
+        let mut dest = Vec::with_capacity(self.io_bytes());
+        // No section is present, but the header keeps the flags of the mapper.
+        let sections = Sections::default();
+        sections.serialize_header_into_vec(&self.mapper, &mut dest);
+        self.mapper.serialize_into_vec(&mut dest);
+        dest.extend_from_slice(&u32::try_from(self.nodes.len()).unwrap().to_le_bytes());
+        for node in &self.nodes {
+            dest.extend_from_slice(&node.serialize());
+        }
+        for link in &self.links {
+            dest.extend_from_slice(&link.serialize());
+        }
+        sections.serialize_into_vec(&self.mapper, &mut dest);
+        dest
+    }
+
+    /// Deserializes the data structure from a given byte slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A source byte slice.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the data structure and the slice not used for the deserialization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::AcTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = AcTrie::from_keys(&keys).unwrap();
+    ///
+    /// let bytes = trie.serialize_to_vec();
+    /// let (other, _) = AcTrie::deserialize_from_slice(&bytes);
+    ///
+    /// assert_eq!(trie.io_bytes(), other.io_bytes());
+    /// ```
+    pub fn deserialize_from_slice(source: &[u8]) -> (Self, &[u8]) {
+        let (mask, source) = Sections::deserialize_header_from_slice(source);
+        let (mut mapper, mut source) = CodeMapper::deserialize_from_slice(source);
+        let len = u32::from_le_bytes(source[..4].try_into().unwrap()) as usize;
+        source = &source[4..];
+        let nodes = {
+            let mut nodes = Vec::with_capacity(len);
+            for _ in 0..len {
+                nodes.push(Node::deserialize(
+                    source[..Node::io_bytes()].try_into().unwrap(),
+                ));
+                source = &source[Node::io_bytes()..];
+            }
+            nodes
+        };
+        let links = {
+            let mut links = Vec::with_capacity(len);
+            for _ in 0..len {
+                links.push(Link::deserialize(
+                    source[..Link::io_bytes()].try_into().unwrap(),
+                ));
+                source = &source[Link::io_bytes()..];
+            }
+            links
+        };
+        let (_, source) = Sections::deserialize_from_slice(mask, &mut mapper, source);
+        let max_depth = links.iter().map(|link| link.depth).max().unwrap_or(0);
+        (
+            Self {
+                mapper,
+                nodes,
+                links,
+                max_depth,
+            },
+            source,
+        )
+    }
+
+    /// Returns an iterator for finding all occurrences of keys in an input haystack.
+    ///
+    /// The haystack is scanned only once, and the iterator reports [`Match`]es ordered by
+    /// their ending positions. Occurrences ending at the same position are ordered from
+    /// the longest one.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::AcTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "界中", "国民"];
+    /// let trie = AcTrie::from_keys(&keys).unwrap();
+    ///
+    /// let haystack = "国民が世界中にて";
+    /// let matches: Vec<_> = trie
+    ///     .find_overlapping_iter(haystack)
+    ///     .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+    ///     .collect();
+    ///
+    /// assert_eq!(
+    ///     matches,
+    ///     vec![
+    ///         (3, "国民", 0..2),
+    ///         (0, "世界", 3..5),
+    ///         (1, "世界中", 3..6),
+    ///         (2, "界中", 4..6),
+    ///     ]
+    /// );
+    /// ```
+    pub fn find_overlapping_iter<'h>(&self, haystack: &'h str) -> FindOverlappingIter<'_, 'h> {
+        FindOverlappingIter {
+            haystack,
+            pos: 0,
+            pos_in_chars: 0,
+            pos_in_units: 0,
+            positions: Positions::new(self.max_depth),
+            trie: self,
+            state_idx: 0,
+            output_idx: INVALID_IDX,
+        }
+    }
+
+    /// Returns an iterator for finding non-overlapping occurrences of keys in an input haystack.
+    ///
+    /// The semantics are the same as [`Trie::find_iter()`](crate::Trie::find_iter).
+    /// The haystack is scanned with the automaton, and only the characters after the end of
+    /// each reported occurrence are rescanned, whose number is bounded by the maximum length
+    /// of keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    /// - `match_kind`: Semantics to choose an occurrence among those starting at the same position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{AcTrie, MatchKind};
+    ///
+    /// let records = vec![("世界", 1), ("世界中", 2), ("中国", 0), ("国民", 3)];
+    /// let trie = AcTrie::from_records(records).unwrap();
+    ///
+    /// let haystack = "世界中国民";
+    ///
+    /// let matches: Vec<_> = trie
+    ///     .find_iter(haystack, MatchKind::LeftmostLongest)
+    ///     .map(|m| &haystack[m.range()])
+    ///     .collect();
+    /// assert_eq!(matches, vec!["世界中", "国民"]);
+    ///
+    /// let matches: Vec<_> = trie
+    ///     .find_iter(haystack, MatchKind::LeftmostShortest)
+    ///     .map(|m| &haystack[m.range()])
+    ///     .collect();
+    /// assert_eq!(matches, vec!["世界", "中国"]);
+    /// ```
+    pub fn find_iter<'h>(&self, haystack: &'h str, match_kind: MatchKind) -> FindIter<'_, 'h> {
+        FindIter {
+            haystack,
+            pos: 0,
+            pos_in_chars: 0,
+            pos_in_units: 0,
+            positions: Positions::new(self.max_depth),
+            trie: self,
+            match_kind,
+        }
+    }
+
+    /// Returns the next state from `state_idx` with character `c`.
+    #[inline(always)]
+    fn next_state(&self, mut state_idx: u32, c: char) -> u32 {
+        let mc = match self.mapper.get(c).filter(|&mc| mc != END_CODE) {
+            Some(mc) => mc,
+            None => return 0,
+        };
+        loop {
+            if let Some(child_idx) = self.get_child_idx(state_idx, mc) {
+                return child_idx;
+            }
+            if state_idx == 0 {
+                return 0;
+            }
+            state_idx = self.link_ref(state_idx).fail;
+        }
+    }
+
+    /// Returns the first state to be reported at `state_idx`.
+    #[inline(always)]
+    fn first_output(&self, state_idx: u32) -> u32 {
+        if self.get_state_value(state_idx).is_some() {
+            state_idx
+        } else {
+            self.link_ref(state_idx).output
+        }
+    }
+
+    /// Returns the value associated with the key of `state_idx` if exists.
+    #[inline(always)]
+    fn get_state_value(&self, state_idx: u32) -> Option<u32> {
+        if self.is_leaf(state_idx) {
+            Some(self.get_value(state_idx))
+        } else if self.has_leaf(state_idx) {
+            Some(self.get_value(self.get_leaf_idx(state_idx)))
+        } else {
+            None
+        }
+    }
+
+    /// Checks if `node_idx` is a state of the automaton, i.e., a non-vacant node that is not
+    /// the root and is not a leaf indicated with END_CODE.
+    #[inline(always)]
+    fn is_state(&self, node_idx: u32) -> bool {
+        if node_idx == 0 || self.node_ref(node_idx).is_vacant() {
+            return false;
+        }
+        let parent_idx = self.get_check(node_idx);
+        self.get_base(parent_idx) ^ END_CODE != node_idx
+    }
+
+    #[inline(always)]
+    fn get_child_idx(&self, node_idx: u32, mc: u32) -> Option<u32> {
+        if self.is_leaf(node_idx) {
+            return None;
+        }
+        Some(self.get_base(node_idx) ^ mc)
+            .filter(|&child_idx| self.get_check(child_idx) == node_idx)
+    }
+
+    #[inline(always)]
+    fn link_ref(&self, node_idx: u32) -> &Link {
+        &self.links[usize::try_from(node_idx).unwrap()]
+    }
+
+    #[inline(always)]
+    fn node_ref(&self, node_idx: u32) -> &Node {
+        &self.nodes[usize::try_from(node_idx).unwrap()]
+    }
+
+    #[inline(always)]
+    fn get_base(&self, node_idx: u32) -> u32 {
+        self.node_ref(node_idx).get_base()
+    }
+
+    #[inline(always)]
+    fn get_check(&self, node_idx: u32) -> u32 {
+        self.node_ref(node_idx).get_check()
+    }
+
+    #[inline(always)]
+    fn is_leaf(&self, node_idx: u32) -> bool {
+        self.node_ref(node_idx).is_leaf()
+    }
+
+    #[inline(always)]
+    fn has_leaf(&self, node_idx: u32) -> bool {
+        self.node_ref(node_idx).has_leaf()
+    }
+
+    #[inline(always)]
+    fn get_leaf_idx(&self, node_idx: u32) -> u32 {
+        let leaf_idx = self.get_base(node_idx) ^ END_CODE;
+        debug_assert_eq!(self.get_check(leaf_idx), node_idx);
+        leaf_idx
+    }
+
+    #[inline(always)]
+    fn get_value(&self, node_idx: u32) -> u32 {
+        debug_assert!(self.is_leaf(node_idx));
+        self.node_ref(node_idx).get_base()
+    }
+
+    #[inline(always)]
+    fn num_nodes(&self) -> u32 {
+        self.nodes.len().try_into().unwrap()
+    }
+
+    /// Returns the total amount of heap used by this automaton in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.mapper.heap_bytes()
+            + self.nodes.len() * mem::size_of::<Node>()
+            + self.links.len() * mem::size_of::<Link>()
+    }
+
+    /// Returns the total amount of bytes to serialize the data structure.
+    pub fn io_bytes(&self) -> usize {
+        self.mapper.io_bytes()
+            + self.nodes.len() * Node::io_bytes()
+            + self.links.len() * Link::io_bytes()
+            + mem::size_of::<u32>()
+            + Sections::default().io_bytes(&self.mapper)
+    }
+
+    /// Returns the number of reserved elements.
+    pub fn num_elems(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of vacant elements.
+    ///
+    /// # Note
+    ///
+    /// It takes `O(num_elems)` time.
+    pub fn num_vacants(&self) -> usize {
+        self.nodes.iter().filter(|nd| nd.is_vacant()).count()
+    }
+}
+
+/// Ring buffer of the positions of the last characters in bytes and in characters,
+/// used to restore the starting position of an occurrence.
+///
+/// The buffer is indexed by the number of units fed into the automaton, since a unit consists
+/// of two characters if a sound mark is composed.
+struct Positions {
+    buf: Vec<(usize, usize)>,
+    mask: usize,
+}
+
+impl Positions {
+    fn new(max_depth: u32) -> Self {
+        let len = usize::try_from(max_depth).unwrap().next_power_of_two();
+        Self {
+            buf: vec![(0, 0); len],
+            mask: len - 1,
+        }
+    }
+
+    /// Records that the `pos_in_units`-th unit starts at `pos` in bytes
+    /// and at `pos_in_chars` in characters.
+    #[inline(always)]
+    fn set(&mut self, pos_in_units: usize, pos: usize, pos_in_chars: usize) {
+        self.buf[pos_in_units & self.mask] = (pos, pos_in_chars);
+    }
+
+    /// Returns the starting position in characters of the key of `state_idx`
+    /// ending at the given position.
+    #[inline(always)]
+    fn start_in_chars(
+        &self,
+        trie: &AcTrie,
+        state_idx: u32,
+        end_in_chars: usize,
+        end_in_units: usize,
+    ) -> usize {
+        match usize::try_from(trie.link_ref(state_idx).depth).unwrap() {
+            0 => end_in_chars,
+            depth => self.buf[(end_in_units - depth) & self.mask].1,
+        }
+    }
+
+    /// Returns the occurrence of the key of `state_idx` ending at the given position.
+    #[inline(always)]
+    fn make_match(
+        &self,
+        trie: &AcTrie,
+        state_idx: u32,
+        end: usize,
+        end_in_chars: usize,
+        end_in_units: usize,
+    ) -> Match {
+        let depth = usize::try_from(trie.link_ref(state_idx).depth).unwrap();
+        let (start, start_in_chars) = self.buf[(end_in_units - depth) & self.mask];
+        Match::new(
+            trie.get_state_value(state_idx).unwrap(),
+            start,
+            end,
+            start_in_chars,
+            end_in_chars,
+        )
+    }
+}
+
+/// Iterator for finding all occurrences of keys in a haystack.
+pub struct FindOverlappingIter<'t, 'h> {
+    haystack: &'h str,
+    pos: usize,
+    pos_in_chars: usize,
+    pos_in_units: usize,
+    positions: Positions,
+    trie: &'t AcTrie,
+    state_idx: u32,
+    output_idx: u32,
+}
+
+impl Iterator for FindOverlappingIter<'_, '_> {
+    type Item = Match;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.output_idx == INVALID_IDX {
+            let (c, len, n) =
+                normalizer::compose_first(&self.haystack[self.pos..], self.trie.mapper.composes())?;
+            self.positions
+                .set(self.pos_in_units, self.pos, self.pos_in_chars);
+            self.pos += len;
+            self.pos_in_chars += n;
+            self.pos_in_units += 1;
+            self.state_idx = self.trie.next_state(self.state_idx, c);
+            self.output_idx = self.trie.first_output(self.state_idx);
+        }
+        let output_idx = self.output_idx;
+        self.output_idx = self.trie.link_ref(output_idx).output;
+        Some(self.positions.make_match(
+            self.trie,
+            output_idx,
+            self.pos,
+            self.pos_in_chars,
+            self.pos_in_units,
+        ))
+    }
+}
+
+/// Iterator for finding non-overlapping occurrences of keys in a haystack.
+pub struct FindIter<'t, 'h> {
+    haystack: &'h str,
+    pos: usize,
+    pos_in_chars: usize,
+    pos_in_units: usize,
+    positions: Positions,
+    trie: &'t AcTrie,
+    match_kind: MatchKind,
+}
+
+impl Iterator for FindIter<'_, '_> {
+    type Item = Match;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let trie = self.trie;
+        let mut state_idx = 0;
+        let mut found: Option<Match> = None;
+        while let Some((c, len, n)) =
+            normalizer::compose_first(&self.haystack[self.pos..], trie.mapper.composes())
+        {
+            self.positions
+                .set(self.pos_in_units, self.pos, self.pos_in_chars);
+            self.pos += len;
+            self.pos_in_chars += n;
+            self.pos_in_units += 1;
+            state_idx = trie.next_state(state_idx, c);
+
+            // No occurrence starting at or before the candidate can be found hereafter.
+            if let Some(m) = found {
+                let start_in_chars = self.positions.start_in_chars(
+                    trie,
+                    state_idx,
+                    self.pos_in_chars,
+                    self.pos_in_units,
+                );
+                if start_in_chars > m.start_in_chars() {
+                    break;
+                }
+            }
+
+            // Outputs are visited from the longest one, i.e., the leftmost one.
+            let mut output_idx = trie.first_output(state_idx);
+            while output_idx != INVALID_IDX {
+                let m = self.positions.make_match(
+                    trie,
+                    output_idx,
+                    self.pos,
+                    self.pos_in_chars,
+                    self.pos_in_units,
+                );
+                let replace = found.map_or(true, |f| {
+                    m.start_in_chars() < f.start_in_chars()
+                        || m.start_in_chars() == f.start_in_chars()
+                            && match self.match_kind {
+                                MatchKind::LeftmostLongest => true,
+                                MatchKind::LeftmostShortest => false,
+                                MatchKind::LeftmostFirst => m.value() < f.value(),
+                            }
+                });
+                if replace {
+                    found = Some(m);
+                }
+                output_idx = trie.link_ref(output_idx).output;
+            }
+        }
+        // Resumes the search at the end of the occurrence.
+        let m = found?;
+        self.pos = m.end();
+        self.pos_in_chars = m.end_in_chars();
+        Some(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Trie;
+
+    use alloc::string::String;
+
+    // Generates a pseudo-random string over a small alphabet.
+    fn random_string(seed: &mut u64, len: usize) -> String {
+        let alphabet = ['あ', 'い', 'う', 'a', 'b'];
+        (0..len)
+            .map(|_| {
+                *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                alphabet[usize::try_from(*seed >> 33).unwrap() % alphabet.len()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_overlapping_iter() {
+        let keys = vec!["世界", "世界中", "界中", "中", "世論調査", "論調"];
+        let trie = AcTrie::from_keys(&keys).unwrap();
+
+        let haystack = "世界中の世論調査";
+        let matches: Vec<_> = trie
+            .find_overlapping_iter(haystack)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (0, "世界", 0..2),
+                (1, "世界中", 0..3),
+                (2, "界中", 1..3),
+                (3, "中", 2..3),
+                (5, "論調", 5..7),
+                (4, "世論調査", 4..8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_random() {
+        let mut seed = 13;
+        for _ in 0..20 {
+            let mut keys: Vec<_> = (0..30)
+                .map(|i| random_string(&mut seed, i % 5 + 1))
+                .collect();
+            keys.sort_unstable();
+            keys.dedup();
+            let actrie = AcTrie::from_keys(&keys).unwrap();
+            let trie = Trie::from_keys(&keys).unwrap();
+
+            let haystack = random_string(&mut seed, 200);
+
+            let mut expected: Vec<_> = trie.find_overlapping_iter(&haystack).collect();
+            let mut results: Vec<_> = actrie.find_overlapping_iter(&haystack).collect();
+            expected.sort_unstable_by_key(|m| (m.start(), m.end()));
+            results.sort_unstable_by_key(|m| (m.start(), m.end()));
+            assert_eq!(results, expected);
+
+            for match_kind in [
+                MatchKind::LeftmostLongest,
+                MatchKind::LeftmostShortest,
+                MatchKind::LeftmostFirst,
+            ] {
+                let expected: Vec<_> = trie.find_iter(&haystack, match_kind).collect();
+                let results: Vec<_> = actrie.find_iter(&haystack, match_kind).collect();
+                assert_eq!(results, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+        let trie = AcTrie::from_keys(&keys).unwrap();
+
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+
+        let (other, remain) = AcTrie::deserialize_from_slice(&bytes);
+        assert!(remain.is_empty());
+
+        assert_eq!(trie.mapper, other.mapper);
+        assert_eq!(trie.nodes, other.nodes);
+        assert_eq!(trie.links, other.links);
+        assert_eq!(trie.max_depth, other.max_depth);
+    }
+
+    #[test]
+    fn test_compose() {
+        let keys = vec!["ｸﾞﾗﾀﾝ", "ﾊﾟﾝ", "ﾀﾝ"];
+        let trie = crate::Builder::new()
+            .normalizer(crate::Normalizer::japanese())
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_actrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (trie, _) = AcTrie::deserialize_from_slice(&bytes);
+
+        let haystack = "ﾊﾟﾝとぐらたん";
+        let matches: Vec<_> = trie
+            .find_overlapping_iter(haystack)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![(1, "ﾊﾟﾝ", 0..3), (0, "ぐらたん", 4..8), (2, "たん", 6..8)]
+        );
+        let matches: Vec<_> = trie
+            .find_iter("ｸﾞﾗﾀﾝとﾊﾟﾝ", MatchKind::LeftmostLongest)
+            .map(|m| (m.value(), m.range(), m.range_in_chars()))
+            .collect();
+        assert_eq!(matches, vec![(0, 0..15, 0..5), (1, 18..27, 6..9)]);
+    }
+}
@@ -0,0 +1,378 @@
+//! Automata for constrained search on dictionary keys.
+//!
+//! An [`Automaton`] can be intersected with a trie through
+//! [`Trie::search_with()`](crate::Trie::search_with) or
+//! [`MpTrie::search_with()`](crate::MpTrie::search_with),
+//! which enumerates all keys accepted by the automaton.
+use crate::utils::{self, WildcardToken};
+
+use alloc::vec::Vec;
+
+/// Automaton reading keys character by character.
+///
+/// The search visits the trie in depth-first order and stops descending into
+/// a subtree as soon as [`Automaton::can_match()`] returns `false`.
+pub trait Automaton {
+    /// State of the automaton.
+    type State: Clone;
+
+    /// Returns the start state.
+    fn start(&self) -> Self::State;
+
+    /// Returns the state after reading `c` from `state`.
+    fn accept(&self, state: &Self::State, c: char) -> Self::State;
+
+    /// Checks if `state` is an accepting state.
+    fn is_match(&self, state: &Self::State) -> bool;
+
+    /// Checks if an accepting state can be reached from `state`.
+    ///
+    /// The default implementation always returns `true`.
+    fn can_match(&self, _state: &Self::State) -> bool {
+        true
+    }
+
+    /// Returns an automaton accepting keys accepted by both `self` and `other`.
+    fn intersection<B>(self, other: B) -> Intersection<Self, B>
+    where
+        Self: Sized,
+        B: Automaton,
+    {
+        Intersection { a: self, b: other }
+    }
+}
+
+impl<A> Automaton for &A
+where
+    A: Automaton + ?Sized,
+{
+    type State = A::State;
+
+    #[inline(always)]
+    fn start(&self) -> Self::State {
+        (*self).start()
+    }
+
+    #[inline(always)]
+    fn accept(&self, state: &Self::State, c: char) -> Self::State {
+        (*self).accept(state, c)
+    }
+
+    #[inline(always)]
+    fn is_match(&self, state: &Self::State) -> bool {
+        (*self).is_match(state)
+    }
+
+    #[inline(always)]
+    fn can_match(&self, state: &Self::State) -> bool {
+        (*self).can_match(state)
+    }
+}
+
+/// Automaton accepting keys within a Levenshtein distance from a query.
+///
+/// # Examples
+///
+/// ```
+/// use crawdad::automaton::Levenshtein;
+/// use crawdad::Trie;
+///
+/// let keys = vec!["世界", "世界中", "世論", "東京"];
+/// let trie = Trie::from_keys(&keys).unwrap();
+///
+/// let results: Vec<_> = trie
+///     .search_with(Levenshtein::new("世界".chars(), 1))
+///     .collect();
+/// assert_eq!(
+///     results,
+///     vec![
+///         ("世界".to_string(), 0),
+///         ("世界中".to_string(), 1),
+///         ("世論".to_string(), 2),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Levenshtein {
+    query: Vec<char>,
+    max_distance: usize,
+}
+
+impl Levenshtein {
+    /// Creates a new automaton.
+    ///
+    /// # Arguments
+    ///
+    /// - `query`: Query string.
+    /// - `max_distance`: Maximum distance.
+    pub fn new<I>(query: I, max_distance: usize) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        Self {
+            query: query.into_iter().collect(),
+            max_distance,
+        }
+    }
+}
+
+impl Automaton for Levenshtein {
+    type State = Vec<usize>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::State {
+        (0..=self.query.len()).collect()
+    }
+
+    #[inline(always)]
+    fn accept(&self, state: &Self::State, c: char) -> Self::State {
+        let mut next = vec![0; state.len()];
+        utils::levenshtein_step(&self.query, state, &mut next, &c);
+        next
+    }
+
+    #[inline(always)]
+    fn is_match(&self, state: &Self::State) -> bool {
+        state[self.query.len()] <= self.max_distance
+    }
+
+    #[inline(always)]
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.iter().any(|&d| d <= self.max_distance)
+    }
+}
+
+/// Automaton accepting keys matching a wildcard pattern.
+///
+/// `?` matches any single character and `*` matches any sequence of characters.
+/// A backslash makes the following character a literal, e.g., `\?` matches `?` itself.
+///
+/// # Examples
+///
+/// ```
+/// use crawdad::automaton::Wildcard;
+/// use crawdad::Trie;
+///
+/// let keys = vec!["世界", "世界中", "世論", "東京"];
+/// let trie = Trie::from_keys(&keys).unwrap();
+///
+/// let results: Vec<_> = trie.search_with(Wildcard::new("世*".chars())).collect();
+/// assert_eq!(
+///     results,
+///     vec![
+///         ("世界".to_string(), 0),
+///         ("世界中".to_string(), 1),
+///         ("世論".to_string(), 2),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Wildcard {
+    pattern: Vec<WildcardToken<char>>,
+}
+
+impl Wildcard {
+    /// Creates a new automaton.
+    ///
+    /// # Arguments
+    ///
+    /// - `pattern`: Wildcard pattern.
+    pub fn new<I>(pattern: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        Self {
+            pattern: utils::parse_wildcard(pattern),
+        }
+    }
+}
+
+impl Automaton for Wildcard {
+    type State = Vec<bool>;
+
+    #[inline(always)]
+    fn start(&self) -> Self::State {
+        let mut state = vec![false; self.pattern.len() + 1];
+        state[0] = true;
+        utils::wildcard_closure(&self.pattern, &mut state);
+        state
+    }
+
+    #[inline(always)]
+    fn accept(&self, state: &Self::State, c: char) -> Self::State {
+        let mut next = vec![false; state.len()];
+        utils::wildcard_step(&self.pattern, state, &mut next, &c);
+        next
+    }
+
+    #[inline(always)]
+    fn is_match(&self, state: &Self::State) -> bool {
+        state[self.pattern.len()]
+    }
+
+    #[inline(always)]
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.iter().any(|&reached| reached)
+    }
+}
+
+/// Automaton accepting keys containing a query as a (not necessarily contiguous) subsequence.
+///
+/// # Examples
+///
+/// ```
+/// use crawdad::automaton::Subsequence;
+/// use crawdad::Trie;
+///
+/// let keys = vec!["世界", "世界中", "世論調査", "東京"];
+/// let trie = Trie::from_keys(&keys).unwrap();
+///
+/// let results: Vec<_> = trie.search_with(Subsequence::new("世中".chars())).collect();
+/// assert_eq!(results, vec![("世界中".to_string(), 1)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Subsequence {
+    query: Vec<char>,
+}
+
+impl Subsequence {
+    /// Creates a new automaton.
+    ///
+    /// # Arguments
+    ///
+    /// - `query`: Query string.
+    pub fn new<I>(query: I) -> Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        Self {
+            query: query.into_iter().collect(),
+        }
+    }
+}
+
+impl Automaton for Subsequence {
+    type State = usize;
+
+    #[inline(always)]
+    fn start(&self) -> Self::State {
+        0
+    }
+
+    #[inline(always)]
+    fn accept(&self, &state: &Self::State, c: char) -> Self::State {
+        if self.query.get(state) == Some(&c) {
+            state + 1
+        } else {
+            state
+        }
+    }
+
+    #[inline(always)]
+    fn is_match(&self, &state: &Self::State) -> bool {
+        state == self.query.len()
+    }
+}
+
+/// Automaton accepting keys whose characters all satisfy a predicate,
+/// which is useful for char-class constraints.
+///
+/// # Examples
+///
+/// ```
+/// use crawdad::automaton::{Automaton, CharFilter, Wildcard};
+/// use crawdad::Trie;
+///
+/// let keys = vec!["カニ", "カニ鍋", "蟹", "蟹鍋"];
+/// let trie = Trie::from_keys(&keys).unwrap();
+///
+/// let katakana = CharFilter::new(|c| ('\u{30a0}'..='\u{30ff}').contains(&c));
+/// let results: Vec<_> = trie
+///     .search_with(Wildcard::new("*鍋".chars()).intersection(CharFilter::new(|c| c != '蟹')))
+///     .collect();
+/// assert_eq!(results, vec![("カニ鍋".to_string(), 1)]);
+///
+/// let results: Vec<_> = trie.search_with(katakana).collect();
+/// assert_eq!(results, vec![("カニ".to_string(), 0)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CharFilter<F> {
+    predicate: F,
+}
+
+impl<F> CharFilter<F>
+where
+    F: Fn(char) -> bool,
+{
+    /// Creates a new automaton.
+    ///
+    /// # Arguments
+    ///
+    /// - `predicate`: Predicate that every character of accepted keys satisfies.
+    pub const fn new(predicate: F) -> Self {
+        Self { predicate }
+    }
+}
+
+impl<F> Automaton for CharFilter<F>
+where
+    F: Fn(char) -> bool,
+{
+    type State = bool;
+
+    #[inline(always)]
+    fn start(&self) -> Self::State {
+        true
+    }
+
+    #[inline(always)]
+    fn accept(&self, &state: &Self::State, c: char) -> Self::State {
+        state && (self.predicate)(c)
+    }
+
+    #[inline(always)]
+    fn is_match(&self, &state: &Self::State) -> bool {
+        state
+    }
+
+    #[inline(always)]
+    fn can_match(&self, &state: &Self::State) -> bool {
+        state
+    }
+}
+
+/// Automaton accepting keys accepted by both of two automata,
+/// created by [`Automaton::intersection()`].
+#[derive(Clone, Debug)]
+pub struct Intersection<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> Automaton for Intersection<A, B>
+where
+    A: Automaton,
+    B: Automaton,
+{
+    type State = (A::State, B::State);
+
+    #[inline(always)]
+    fn start(&self) -> Self::State {
+        (self.a.start(), self.b.start())
+    }
+
+    #[inline(always)]
+    fn accept(&self, state: &Self::State, c: char) -> Self::State {
+        (self.a.accept(&state.0, c), self.b.accept(&state.1, c))
+    }
+
+    #[inline(always)]
+    fn is_match(&self, state: &Self::State) -> bool {
+        self.a.is_match(&state.0) && self.b.is_match(&state.1)
+    }
+
+    #[inline(always)]
+    fn can_match(&self, state: &Self::State) -> bool {
+        self.a.can_match(&state.0) && self.b.can_match(&state.1)
+    }
+}
@@ -1,10 +1,18 @@
+//! Builder of the data structures with optional features.
 use crate::errors::{CrawdadError, Result};
+use crate::infix::InfixIndex;
 use crate::mapper::CodeMapper;
-use crate::{utils, MpTrie, Node, Trie};
+use crate::normalizer::{Composer, Normalizer};
+use crate::sections::Sections;
+use crate::siblings::Siblings;
+use crate::sparse::SparseValues;
+use crate::{utils, AcTrie, MpTrie, Node, Trie};
 use crate::{END_CODE, END_MARKER, INVALID_IDX, MAX_VALUE, OFFSET_MASK};
 
 use core::cmp::Ordering;
+use core::mem;
 
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 // The default parameter for free blocks to be searched in `find_base`.
@@ -16,17 +24,101 @@ struct Record {
     value: u32,
 }
 
+// Mapping from characters to their canonical forms in keys,
+// composed of the normalizer and the equivalence classes.
+struct Canonicalizer {
+    normalizer: Normalizer,
+    classes: BTreeMap<char, char>,
+}
+
+impl Canonicalizer {
+    fn new(normalizer: Option<Normalizer>, classes: &[(char, char)]) -> Result<Self> {
+        let normalizer = normalizer.unwrap_or_default();
+        let mut map = BTreeMap::new();
+        for &(c, r) in classes {
+            let (c, r) = (normalizer.normalize(c), normalizer.normalize(r));
+            if c == r {
+                continue;
+            }
+            if map.insert(c, r).map_or(false, |s| s != r) {
+                return Err(CrawdadError::input(
+                    "a character must not be given two representatives.",
+                ));
+            }
+        }
+        if map.values().any(|r| map.contains_key(r)) {
+            return Err(CrawdadError::input(
+                "representatives must not be mapped to other characters.",
+            ));
+        }
+        Ok(Self {
+            normalizer,
+            classes: map,
+        })
+    }
+
+    #[inline(always)]
+    fn get(&self, c: char) -> char {
+        let c = self.normalizer.normalize(c);
+        self.classes.get(&c).copied().unwrap_or(c)
+    }
+
+    /// Returns the canonical form of a key, in which kana and the following sound marks are
+    /// composed if the normalizer does so.
+    fn key(&self, key: &str) -> Vec<char> {
+        Composer::new(key.chars(), self.normalizer.composes())
+            .map(|(c, _)| self.get(c))
+            .collect()
+    }
+
+    /// Returns pairs of characters and their canonical forms that differ.
+    fn aliases(&self) -> Vec<(char, char)> {
+        let mut aliases: Vec<_> = self
+            .normalizer
+            .aliases()
+            .map(|(c, _)| c)
+            .chain(self.classes.keys().copied())
+            .map(|c| (c, self.get(c)))
+            .filter(|(c, r)| c != r)
+            .collect();
+        aliases.sort_unstable();
+        aliases.dedup();
+        aliases
+    }
+}
+
 #[derive(Default, Debug, PartialEq, Eq)]
 struct Suffix {
     key: Vec<char>,
     value: u32,
 }
 
+/// Builder of [`Trie`], [`MpTrie`], and [`AcTrie`] with optional features.
+///
+/// # Examples
+///
+/// ```
+/// use crawdad::Builder;
+///
+/// let keys = vec!["世界", "世界中", "国民"];
+/// let trie = Builder::new()
+///     .reverse_index()
+///     .build_from_keys(&keys)
+///     .unwrap()
+///     .release_trie()
+///     .unwrap();
+///
+/// assert_eq!(trie.restore_key(1), Some("世界中".to_string()));
+/// ```
 pub struct Builder {
     records: Vec<Record>,
     mapper: CodeMapper,
     nodes: Vec<Node>,
     suffixes: Option<Vec<Suffix>>,
+    sections: Sections,
+    weights: Option<Vec<u32>>,
+    normalizer: Option<Normalizer>,
+    char_classes: Vec<(char, char)>,
     labels: Vec<u32>,
     head_idx: u32,
     block_len: u32,
@@ -40,6 +132,10 @@ impl Default for Builder {
             mapper: CodeMapper::default(),
             nodes: vec![],
             suffixes: None,
+            sections: Sections::default(),
+            weights: None,
+            normalizer: None,
+            char_classes: vec![],
             labels: vec![],
             head_idx: 0,
             block_len: 0,
@@ -49,16 +145,139 @@ impl Default for Builder {
 }
 
 impl Builder {
+    /// Creates a new [`Builder`] with the default configuration.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Enables the minimal-prefix trie form, which is required to build [`MpTrie`].
     #[allow(clippy::missing_const_for_fn)]
     pub fn minimal_prefix(mut self) -> Self {
         self.suffixes = Some(vec![]);
         self
     }
 
+    /// Enables the reverse index to restore keys from their values
+    /// with [`Trie::restore_key()`] or [`MpTrie::restore_key()`].
+    ///
+    /// The values must be distinct integers less than the number of keys,
+    /// as assigned by [`Self::build_from_keys()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn reverse_index(mut self) -> Self {
+        self.sections.leaf_idxs = Some(vec![]);
+        self
+    }
+
+    /// Enables dense key ids in `[0..n-1]` assigned to keys in the lexicographical order,
+    /// where `n` is the number of keys, independently of the values.
+    ///
+    /// The ids are available with [`Trie::key_id()`] and [`Trie::key_at()`]
+    /// (or [`MpTrie::key_id()`] and [`MpTrie::key_at()`]).
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn key_ids(mut self) -> Self {
+        self.sections.ranks = Some(SparseValues::default());
+        self.sections.key_leaves = Some(vec![]);
+        self
+    }
+
+    /// Enables the infix index to find keys containing a pattern
+    /// with [`Trie::contains_search()`] or [`MpTrie::contains_search()`].
+    ///
+    /// The index is a trie of all the suffixes of keys,
+    /// so its size grows with the squared lengths of keys.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn infix_index(mut self) -> Self {
+        self.sections.infix = Some(InfixIndex::default());
+        self
+    }
+
+    /// Enables the counts of keys in subtrees to find the number of keys starting with a prefix
+    /// with [`Trie::count_prefix()`] or [`MpTrie::count_prefix()`].
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn prefix_counts(mut self) -> Self {
+        self.sections.counts = Some(SparseValues::default());
+        self
+    }
+
+    /// Enables the multi-value mode, in which duplicate keys are accepted
+    /// and their values are collected into a list in the input order.
+    ///
+    /// Each key is associated with the id of its list instead of a value,
+    /// where ids in `[0..m-1]` are assigned in the lexicographical order of `m` distinct keys.
+    /// The lists are available with [`Trie::values()`] and [`Trie::exact_match_values()`]
+    /// (or [`MpTrie::values()`] and [`MpTrie::exact_match_values()`]),
+    /// and the values in the lists are not limited by the maximum value.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn multi_values(mut self) -> Self {
+        self.sections.value_offsets = Some(vec![]);
+        self.sections.value_lists = Some(vec![]);
+        self
+    }
+
+    /// Enables the links between siblings to enumerate the children of a node in time
+    /// proportional to their number, which speeds up the searches visiting subtrees such as
+    /// [`Trie::predictive_search()`] and [`Trie::iter()`] on a large alphabet.
+    ///
+    /// The links take two integers for each node and are rebuilt in deserialization.
+    /// Without them, the children are found by probing all characters in the alphabet.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn sibling_links(mut self) -> Self {
+        self.sections.sibling_links = true;
+        self
+    }
+
+    /// Sets the normalizer applied to keys at build time and to haystacks at query time.
+    ///
+    /// Keys that become identical after the normalization are regarded as duplicates.
+    /// Keys restored from the data structure are in their normalized forms.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Sets equivalence classes of characters, given as pairs of characters and
+    /// the representatives of their classes.
+    ///
+    /// Characters in the same class share one code and are treated as identical
+    /// at build and query time. The classes are applied after the normalizer.
+    /// Each character must be given at most one representative, and the representatives
+    /// must not be mapped to other characters, i.e., the classes are not closed transitively.
+    /// Otherwise, building the data structure fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::Builder;
+    ///
+    /// let keys = vec!["高橋", "Tokyo!"];
+    /// let trie = Builder::new()
+    ///     .char_classes([('髙', '高'), ('！', '!')])
+    ///     .char_classes(('A'..='Z').map(|c| (c, c.to_ascii_lowercase())))
+    ///     .build_from_keys(&keys)
+    ///     .unwrap()
+    ///     .release_trie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.exact_match("髙橋".chars()), Some(0));
+    /// assert_eq!(trie.exact_match("TOKYO！".chars()), Some(1));
+    /// ```
+    pub fn char_classes<I>(mut self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = (char, char)>,
+    {
+        self.char_classes.extend(classes);
+        self
+    }
+
+    /// Builds the data structure from input keys.
+    ///
+    /// Values in `[0..n-1]` will be associated with keys in the input order,
+    /// where `n` is the number of keys.
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::build_from_records()`].
     pub fn build_from_keys<I, K>(self, keys: I) -> Result<Self>
     where
         I: IntoIterator<Item = K>,
@@ -71,20 +290,205 @@ impl Builder {
         )
     }
 
+    /// Builds the data structure from input keys and their byte payloads.
+    ///
+    /// Values in `[0..n-1]` will be associated with keys in the input order,
+    /// where `n` is the number of keys, and the payloads are stored in an arena indexed by
+    /// the values. The payloads are available with [`Trie::payload()`] and
+    /// [`Trie::exact_match_payload()`] (or [`MpTrie::payload()`] and
+    /// [`MpTrie::exact_match_payload()`]).
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - the multi-value mode is enabled,
+    /// - the total length of the payloads exceeds the expected one, or
+    /// - [`Self::build_from_records()`] fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::Builder;
+    ///
+    /// let records = vec![("世界", "せかい"), ("世界中", "せかいじゅう"), ("国民", "こくみん")];
+    /// let trie = Builder::new()
+    ///     .build_from_payloads(records)
+    ///     .unwrap()
+    ///     .release_trie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     trie.exact_match_payload("世界中".chars()),
+    ///     Some("せかいじゅう".as_bytes())
+    /// );
+    /// ```
+    pub fn build_from_payloads<I, K, P>(mut self, records: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, P)>,
+        K: AsRef<str>,
+        P: AsRef<[u8]>,
+    {
+        if self.sections.value_offsets.is_some() {
+            return Err(CrawdadError::setup("multi_values must be disabled."));
+        }
+        let mut keys = vec![];
+        let mut payload_offsets = vec![0];
+        let mut payloads = vec![];
+        for (key, payload) in records {
+            keys.push(key);
+            payloads.extend_from_slice(payload.as_ref());
+            payload_offsets.push(
+                u32::try_from(payloads.len())
+                    .map_err(|_| CrawdadError::scale("length of payloads", u32::MAX))?,
+            );
+        }
+        self.sections.payload_offsets = Some(payload_offsets);
+        self.sections.payloads = Some(payloads);
+        self.build_from_keys(keys)
+    }
+
+    /// Builds the data structure from input records with values wider than 31 bits.
+    ///
+    /// Values in `[0..n-1]` will be associated with keys in the input order,
+    /// where `n` is the number of keys, and the wide values are stored in a separate array
+    /// indexed by the values. The wide values are available with [`Trie::wide_value()`],
+    /// [`Trie::exact_match_wide()`], and [`Trie::common_prefix_search_wide()`]
+    /// (or the counterparts of [`MpTrie`]).
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - the multi-value mode is enabled, or
+    /// - [`Self::build_from_records()`] fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::Builder;
+    ///
+    /// let records = vec![("世界", 1 << 40), ("世界中", u64::MAX), ("国民", 0)];
+    /// let trie = Builder::new()
+    ///     .build_from_wide_records(records)
+    ///     .unwrap()
+    ///     .release_trie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.exact_match_wide("世界中".chars()), Some(u64::MAX));
+    /// ```
+    pub fn build_from_wide_records<I, K>(mut self, records: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, u64)>,
+        K: AsRef<str>,
+    {
+        if self.sections.value_offsets.is_some() {
+            return Err(CrawdadError::setup("multi_values must be disabled."));
+        }
+        let mut keys = vec![];
+        let mut wide_values = vec![];
+        for (key, value) in records {
+            keys.push(key);
+            wide_values.push(value);
+        }
+        self.sections.wide_values = Some(wide_values);
+        self.build_from_keys(keys)
+    }
+
+    /// Builds the data structure from input keys and their weights for top-k completion.
+    ///
+    /// Values in `[0..n-1]` will be associated with keys in the input order,
+    /// where `n` is the number of keys, and each node stores the maximum weight of keys
+    /// in its subtree. The heaviest completions of a prefix are available with
+    /// [`Trie::top_k_completions()`] (or [`MpTrie::top_k_completions()`]).
+    ///
+    /// In the multi-value mode, the weight of a key is the maximum of those of its records.
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::build_from_records()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::Builder;
+    ///
+    /// let records = vec![("世界", 5), ("世界中", 8), ("世論調査", 3), ("国民", 10)];
+    /// let trie = Builder::new()
+    ///     .build_from_weighted_keys(records)
+    ///     .unwrap()
+    ///     .release_trie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     trie.top_k_completions("".chars(), 2),
+    ///     Some(vec![("国民".to_string(), 3), ("世界中".to_string(), 1)])
+    /// );
+    /// ```
+    pub fn build_from_weighted_keys<I, K>(mut self, records: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, u32)>,
+        K: AsRef<str>,
+    {
+        let mut keys = vec![];
+        let mut weights = vec![];
+        for (key, weight) in records {
+            keys.push(key);
+            weights.push(weight);
+        }
+        self.weights = Some(weights);
+        self.build_from_keys(keys)
+    }
+
+    /// Builds the data structure from input records.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - `records` is empty,
+    /// - `records` contains empty strings,
+    /// - `records` contains duplicate keys without the multi-value mode,
+    /// - `records` contains values not satisfying the requirement of enabled options,
+    /// - the character classes give a character two representatives or map a representative
+    ///   to another character,
+    /// - the scale of `keys` exceeds the expected one, or
+    /// - the scale of the resulting trie exceeds the expected one.
     pub fn build_from_records<I, K>(mut self, records: I) -> Result<Self>
     where
         I: IntoIterator<Item = (K, u32)>,
         K: AsRef<str>,
     {
+        let canonicalizer = Canonicalizer::new(self.normalizer, &self.char_classes)?;
         self.records = records
             .into_iter()
             .map(|(k, v)| Record {
-                key: k.as_ref().chars().collect(),
+                key: canonicalizer.key(k.as_ref()),
                 value: v,
             })
             .collect();
 
-        self.records.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        if let (Some(value_offsets), Some(value_lists)) = (
+            self.sections.value_offsets.as_mut(),
+            self.sections.value_lists.as_mut(),
+        ) {
+            // The stable sort keeps the input order of values for each key.
+            let mut records = mem::take(&mut self.records);
+            records.sort_by(|a, b| a.key.cmp(&b.key));
+            value_offsets.clear();
+            value_lists.clear();
+            for Record { key, value } in records {
+                if self.records.last().map_or(true, |r| r.key != key) {
+                    value_offsets.push(u32::try_from(value_lists.len()).unwrap());
+                    let id = u32::try_from(self.records.len()).unwrap();
+                    self.records.push(Record { key, value: id });
+                }
+                value_lists.push(value);
+            }
+            value_offsets.push(u32::try_from(value_lists.len()).unwrap());
+        } else {
+            self.records.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        }
 
         for &Record { key: _, value } in &self.records {
             if MAX_VALUE < value {
@@ -92,8 +496,30 @@ impl Builder {
             }
         }
 
+        if let Some(leaf_idxs) = self.sections.leaf_idxs.as_mut() {
+            // Checks that the values can be used as indices of the reverse index.
+            *leaf_idxs = vec![INVALID_IDX; self.records.len()];
+            for &Record { key: _, value } in &self.records {
+                match leaf_idxs.get_mut(usize::try_from(value).unwrap()) {
+                    Some(leaf_idx) if *leaf_idx == INVALID_IDX => *leaf_idx = 0,
+                    _ => {
+                        return Err(CrawdadError::input(
+                            "values must be distinct and less than the number of keys.",
+                        ))
+                    }
+                }
+            }
+        }
+
         self.mapper = CodeMapper::new(&make_freqs(&self.records)?);
         assert_eq!(self.mapper.get(END_MARKER).unwrap(), END_CODE);
+        let aliases = canonicalizer.aliases();
+        for &(alias, c) in &aliases {
+            self.mapper.add_alias(alias, c);
+        }
+        if canonicalizer.normalizer.composes() {
+            self.mapper.enable_composition();
+        }
 
         make_prefix_free(&mut self.records)?;
 
@@ -102,24 +528,91 @@ impl Builder {
         self.arrange_nodes(0, self.records.len(), 0, 0)?;
         self.finish();
 
+        if self.sections.ranks.is_some() {
+            self.rank_leaves();
+        }
+
+        if self.sections.counts.is_some() {
+            self.count_keys();
+        }
+
+        if let Some(weights) = self.weights.take() {
+            self.set_max_weights(&weights);
+        }
+
+        if self.sections.infix.is_some() {
+            self.build_infix(&aliases)?;
+        }
+
         Ok(self)
     }
 
+    /// Releases [`Trie`] from the builder.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    /// the minimal-prefix trie form is enabled.
     #[allow(clippy::missing_const_for_fn)]
     pub fn release_trie(self) -> Result<Trie> {
         if self.suffixes.is_some() {
             Err(CrawdadError::setup("minimal_prefix must be disabled."))
         } else {
-            let Self { nodes, mapper, .. } = self;
-            Ok(Trie { nodes, mapper })
+            let Self {
+                nodes,
+                mapper,
+                sections,
+                ..
+            } = self;
+            Ok(Trie {
+                siblings: Siblings::new(&nodes, &mapper, sections.sibling_links),
+                nodes,
+                mapper,
+                sections,
+            })
+        }
+    }
+
+    /// Releases [`AcTrie`] from the builder.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - the minimal-prefix trie form is enabled,
+    /// - the reverse index is enabled,
+    /// - the dense key ids are enabled,
+    /// - the infix index is enabled,
+    /// - the multi-value mode is enabled,
+    /// - the prefix counts are enabled,
+    /// - the payloads are given,
+    /// - the wide values are given,
+    /// - the weights are given, or
+    /// - the sibling links are enabled.
+    pub fn release_actrie(self) -> Result<AcTrie> {
+        self.sections.check_disabled()?;
+        if self.suffixes.is_some() {
+            return Err(CrawdadError::setup("minimal_prefix must be disabled."));
         }
+        // Sibling links of `Trie` are not needed.
+        let Self { mapper, nodes, .. } = self;
+        Ok(AcTrie::new(mapper, nodes))
     }
 
+    /// Releases [`MpTrie`] from the builder.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - the minimal-prefix trie form is disabled, or
+    /// - the scale of the resulting trie exceeds the expected one.
     pub fn release_mptrie(self) -> Result<MpTrie> {
         let Self {
             mapper,
             mut nodes,
             suffixes,
+            sections,
             ..
         } = self;
 
@@ -178,11 +671,13 @@ impl Builder {
         }
 
         Ok(MpTrie {
+            siblings: Siblings::new(&nodes, &mapper, sections.sibling_links),
             mapper,
             nodes,
             tails,
             code_size,
             value_size,
+            sections,
         })
     }
 
@@ -191,6 +686,102 @@ impl Builder {
         self.nodes.len().try_into().unwrap()
     }
 
+    // This is separated from the generic `build_from_records` to avoid the infinite
+    // instantiation, since the index is built with another builder.
+    fn build_infix(&mut self, aliases: &[(char, char)]) -> Result<()> {
+        self.sections.infix = Some(InfixIndex::new(
+            self.records.iter().map(|r| {
+                let key = r.key.strip_suffix(&[END_MARKER]).unwrap_or(&r.key);
+                (key, r.value)
+            }),
+            aliases,
+        )?);
+        Ok(())
+    }
+
+    /// Assigns the ranks to the leaves, which are the inverse of the leaves in the order of keys.
+    fn rank_leaves(&mut self) {
+        let key_leaves = self.sections.key_leaves.as_ref().unwrap();
+        let mut entries: Vec<_> = key_leaves
+            .iter()
+            .enumerate()
+            .map(|(rank, &leaf_idx)| {
+                (
+                    usize::try_from(leaf_idx).unwrap(),
+                    u32::try_from(rank).unwrap(),
+                )
+            })
+            .collect();
+        entries.sort_unstable();
+        self.sections.ranks = Some(SparseValues::new(self.nodes.len(), entries));
+    }
+
+    /// Counts the keys in the subtree of each node by climbing from the leaves.
+    fn count_keys(&mut self) {
+        let mut counts = vec![0; self.nodes.len()];
+        for leaf_idx in 0..self.num_nodes() {
+            if !self.node_ref(leaf_idx).is_leaf() {
+                continue;
+            }
+            let mut node_idx = leaf_idx;
+            loop {
+                counts[usize::try_from(node_idx).unwrap()] += 1;
+                if node_idx == 0 {
+                    break;
+                }
+                node_idx = self.node_ref(node_idx).get_check();
+            }
+        }
+        // Leaves always have one key, so only the counts of internal nodes are stored.
+        let entries = counts.into_iter().enumerate().filter(|&(node_idx, count)| {
+            count != 0 && !self.node_ref(u32::try_from(node_idx).unwrap()).is_leaf()
+        });
+        self.sections.counts = Some(SparseValues::new(self.nodes.len(), entries));
+    }
+
+    /// Sets the maximum weight of keys in the subtree of each node by climbing from the leaves,
+    /// where `weights` are indexed by the values, or by the values in the lists
+    /// in the multi-value mode.
+    fn set_max_weights(&mut self, weights: &[u32]) {
+        let mut max_weights = vec![0; self.nodes.len()];
+        for leaf_idx in 0..self.num_nodes() {
+            if !self.node_ref(leaf_idx).is_leaf() {
+                continue;
+            }
+            // A leaf in the minimal-prefix form has the index of its suffix instead of the value.
+            let mut value = self.node_ref(leaf_idx).get_base();
+            if let Some(suffixes) = self.suffixes.as_ref() {
+                value = suffixes[usize::try_from(value).unwrap()].value;
+            }
+            let weight = if let (Some(value_offsets), Some(value_lists)) = (
+                self.sections.value_offsets.as_ref(),
+                self.sections.value_lists.as_ref(),
+            ) {
+                // In the multi-value mode, the value is the id of a list of the input indices.
+                let id = usize::try_from(value).unwrap();
+                let start = usize::try_from(value_offsets[id]).unwrap();
+                let end = usize::try_from(value_offsets[id + 1]).unwrap();
+                value_lists[start..end]
+                    .iter()
+                    .map(|&v| weights[usize::try_from(v).unwrap()])
+                    .max()
+                    .unwrap()
+            } else {
+                weights[usize::try_from(value).unwrap()]
+            };
+            let mut node_idx = leaf_idx;
+            loop {
+                let max_weight = &mut max_weights[usize::try_from(node_idx).unwrap()];
+                *max_weight = (*max_weight).max(weight);
+                if node_idx == 0 {
+                    break;
+                }
+                node_idx = self.node_ref(node_idx).get_check();
+            }
+        }
+        self.sections.max_weights = Some(max_weights);
+    }
+
     fn init_array(&mut self) {
         self.nodes.clear();
         self.nodes
@@ -223,7 +814,8 @@ impl Builder {
         debug_assert!(self.is_fixed(node_idx));
 
         if let Some(suffixes) = self.suffixes.as_mut() {
-            if spos + 1 == epos {
+            // The root must not be a leaf even if there is only one key.
+            if spos + 1 == epos && depth != 0 {
                 // It has been checked in build_from_records().
                 debug_assert_eq!(self.records[spos].value & !OFFSET_MASK, 0);
 
@@ -233,6 +825,13 @@ impl Builder {
                     return Err(CrawdadError::scale("length of suffixes", OFFSET_MASK));
                 };
                 self.nodes[usize::try_from(node_idx).unwrap()].base = suffix_idx | !OFFSET_MASK;
+                if let Some(leaf_idxs) = self.sections.leaf_idxs.as_mut() {
+                    leaf_idxs[usize::try_from(self.records[spos].value).unwrap()] = node_idx;
+                }
+                if let Some(key_leaves) = self.sections.key_leaves.as_mut() {
+                    debug_assert_eq!(key_leaves.len(), spos);
+                    key_leaves.push(node_idx);
+                }
                 suffixes.push(Suffix {
                     key: pop_end_marker(&self.records[spos].key[depth..]),
                     value: self.records[spos].value,
@@ -245,6 +844,13 @@ impl Builder {
             debug_assert_eq!(self.records[spos].value & !OFFSET_MASK, 0);
             // Sets IsLeaf = True
             self.node_mut(node_idx).base = self.records[spos].value | !OFFSET_MASK;
+            if let Some(leaf_idxs) = self.sections.leaf_idxs.as_mut() {
+                leaf_idxs[usize::try_from(self.records[spos].value).unwrap()] = node_idx;
+            }
+            if let Some(key_leaves) = self.sections.key_leaves.as_mut() {
+                debug_assert_eq!(key_leaves.len(), spos);
+                key_leaves.push(node_idx);
+            }
             // Note: HasLeaf must not be set here and should be set in finish()
             // because MSB of check is used to indicate vacant element.
             return Ok(());
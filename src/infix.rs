@@ -0,0 +1,274 @@
+use crate::builder::Builder;
+use crate::errors::Result;
+use crate::mapper::CodeMapper;
+use crate::{Node, Trie};
+
+use crate::INVALID_IDX;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use core::mem::size_of;
+
+/// Index to find keys containing a pattern, built on a trie of all the suffixes of keys.
+///
+/// The distinct suffixes are ranked in the lexicographical order, and the values of keys
+/// having each suffix are stored in `values` in that order. Since a subtree of the suffix trie
+/// corresponds to a range of ranks, each node holds the range of `values` in its subtree.
+#[derive(Default)]
+pub struct InfixIndex {
+    mapper: CodeMapper,
+    nodes: Vec<Node>,
+    begins: Vec<u32>,
+    ends: Vec<u32>,
+    values: Vec<u32>,
+}
+
+impl InfixIndex {
+    /// Builds the index from records of canonical keys, where `aliases` are pairs of
+    /// characters and their canonical forms.
+    pub fn new<'a, I>(records: I, aliases: &[(char, char)]) -> Result<Self>
+    where
+        I: IntoIterator<Item = (&'a [char], u32)>,
+    {
+        let mut suffixes = vec![];
+        for (key, value) in records {
+            for i in 0..key.len() {
+                suffixes.push((&key[i..], value));
+            }
+        }
+        suffixes.sort_unstable();
+
+        // Groups the values by the distinct suffixes.
+        let mut heads: Vec<&[char]> = vec![];
+        let mut offsets = vec![];
+        let mut values = Vec::with_capacity(suffixes.len());
+        for (suffix, value) in suffixes {
+            if heads.last() != Some(&suffix) {
+                heads.push(suffix);
+                offsets.push(u32::try_from(values.len()).unwrap());
+            }
+            values.push(value);
+        }
+        offsets.push(u32::try_from(values.len()).unwrap());
+
+        let Trie {
+            mut mapper,
+            nodes,
+            sections,
+            ..
+        } =
+            Builder::new()
+                .reverse_index()
+                .build_from_records(heads.iter().enumerate().map(|(i, suffix)| {
+                    (suffix.iter().collect::<String>(), u32::try_from(i).unwrap())
+                }))?
+                .release_trie()?;
+
+        for &(alias, c) in aliases {
+            mapper.add_alias(alias, c);
+        }
+
+        // Visits the leaves in the order of ranks so that each node has the first and last ones.
+        let mut begins = vec![INVALID_IDX; nodes.len()];
+        let mut ends = vec![INVALID_IDX; nodes.len()];
+        for (rank, &leaf_idx) in sections.leaf_idxs.unwrap().iter().enumerate() {
+            let mut node_idx = usize::try_from(leaf_idx).unwrap();
+            loop {
+                if begins[node_idx] == INVALID_IDX {
+                    begins[node_idx] = offsets[rank];
+                }
+                ends[node_idx] = offsets[rank + 1];
+                if node_idx == 0 {
+                    break;
+                }
+                node_idx = usize::try_from(nodes[node_idx].get_check()).unwrap();
+            }
+        }
+
+        Ok(Self {
+            mapper,
+            nodes,
+            begins,
+            ends,
+            values,
+        })
+    }
+
+    /// Returns the sorted distinct values of keys containing `pattern`.
+    pub fn contains_search<I>(&self, pattern: I) -> Vec<u32>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut node_idx = 0;
+        for c in pattern {
+            if self.nodes[node_idx].is_leaf() {
+                return vec![];
+            }
+            let child_idx = match self.mapper.get(c) {
+                Some(mc) => usize::try_from(self.nodes[node_idx].get_base() ^ mc).unwrap(),
+                None => return vec![],
+            };
+            match self.nodes.get(child_idx) {
+                Some(child) if usize::try_from(child.get_check()).unwrap() == node_idx => {
+                    node_idx = child_idx;
+                }
+                _ => return vec![],
+            }
+        }
+        let begin = usize::try_from(self.begins[node_idx]).unwrap();
+        let end = usize::try_from(self.ends[node_idx]).unwrap();
+        let mut values = self.values[begin..end].to_vec();
+        values.sort_unstable();
+        values.dedup();
+        values
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        self.mapper.heap_bytes()
+            + self.nodes.len() * size_of::<Node>()
+            + (self.begins.len() + self.ends.len() + self.values.len()) * size_of::<u32>()
+    }
+
+    pub fn io_bytes(&self) -> usize {
+        self.mapper.io_bytes()
+            + self.nodes.len() * Node::io_bytes()
+            + size_of::<u32>()
+            + (self.begins.len() + self.ends.len() + self.values.len() + 1) * size_of::<u32>()
+    }
+
+    pub fn serialize_into_vec(&self, dest: &mut Vec<u8>) {
+        self.mapper.serialize_into_vec(dest);
+        dest.extend_from_slice(&u32::try_from(self.nodes.len()).unwrap().to_le_bytes());
+        for (node, (&begin, &end)) in self.nodes.iter().zip(self.begins.iter().zip(&self.ends)) {
+            dest.extend_from_slice(&node.serialize());
+            dest.extend_from_slice(&begin.to_le_bytes());
+            dest.extend_from_slice(&end.to_le_bytes());
+        }
+        dest.extend_from_slice(&u32::try_from(self.values.len()).unwrap().to_le_bytes());
+        for x in &self.values {
+            dest.extend_from_slice(&x.to_le_bytes());
+        }
+    }
+
+    pub fn deserialize_from_slice(source: &[u8]) -> (Self, &[u8]) {
+        let (mapper, mut source) = CodeMapper::deserialize_from_slice(source);
+        let len = u32::from_le_bytes(source[..4].try_into().unwrap()) as usize;
+        source = &source[4..];
+        let mut nodes = Vec::with_capacity(len);
+        let mut begins = Vec::with_capacity(len);
+        let mut ends = Vec::with_capacity(len);
+        for _ in 0..len {
+            nodes.push(Node::deserialize(
+                source[..Node::io_bytes()].try_into().unwrap(),
+            ));
+            source = &source[Node::io_bytes()..];
+            begins.push(u32::from_le_bytes(source[..4].try_into().unwrap()));
+            ends.push(u32::from_le_bytes(source[4..8].try_into().unwrap()));
+            source = &source[8..];
+        }
+        let len = u32::from_le_bytes(source[..4].try_into().unwrap()) as usize;
+        source = &source[4..];
+        let mut values = Vec::with_capacity(len);
+        for _ in 0..len {
+            values.push(u32::from_le_bytes(source[..4].try_into().unwrap()));
+            source = &source[4..];
+        }
+        (
+            Self {
+                mapper,
+                nodes,
+                begins,
+                ends,
+                values,
+            },
+            source,
+        )
+    }
+
+    /// Serializes an optional index into `dest`, where nothing is written if it is absent.
+    pub fn serialize_option(dest: &mut Vec<u8>, index: Option<&Self>) {
+        if let Some(index) = index {
+            index.serialize_into_vec(dest);
+        }
+    }
+
+    /// Deserializes an optional index from `source` if it is `present`.
+    pub fn deserialize_option(present: bool, source: &[u8]) -> (Option<Self>, &[u8]) {
+        if present {
+            let (index, source) = Self::deserialize_from_slice(source);
+            (Some(index), source)
+        } else {
+            (None, source)
+        }
+    }
+
+    /// Returns the number of bytes to serialize an optional index.
+    pub fn option_io_bytes(index: Option<&Self>) -> usize {
+        index.map_or(0, Self::io_bytes)
+    }
+
+    /// Returns the number of bytes of heap used by an optional index.
+    pub fn option_heap_bytes(index: Option<&Self>) -> usize {
+        index.map_or(0, Self::heap_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_search() {
+        let keys = ["abab", "abc", "b", "bca", "cab"];
+        let keys: Vec<Vec<char>> = keys.iter().map(|k| k.chars().collect()).collect();
+        let index = InfixIndex::new(
+            keys.iter()
+                .enumerate()
+                .map(|(i, k)| (k.as_slice(), u32::try_from(i).unwrap())),
+            &[],
+        )
+        .unwrap();
+
+        for pattern in [
+            "", "a", "b", "c", "ab", "ba", "bc", "ca", "abab", "bcab", "d",
+        ] {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let expected: Vec<u32> = keys
+                .iter()
+                .enumerate()
+                .filter(|(_, k)| {
+                    k.windows(pattern.len().max(1))
+                        .any(|w| pattern.is_empty() || w == pattern.as_slice())
+                })
+                .map(|(i, _)| u32::try_from(i).unwrap())
+                .collect();
+            assert_eq!(index.contains_search(pattern.iter().copied()), expected);
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let keys: Vec<Vec<char>> = ["世界", "世界中", "国民"]
+            .iter()
+            .map(|k| k.chars().collect())
+            .collect();
+        let index = InfixIndex::new(
+            keys.iter()
+                .enumerate()
+                .map(|(i, k)| (k.as_slice(), u32::try_from(i).unwrap())),
+            &[],
+        )
+        .unwrap();
+        let mut bytes = vec![];
+        InfixIndex::serialize_option(&mut bytes, Some(&index));
+        assert_eq!(InfixIndex::option_io_bytes(Some(&index)), bytes.len());
+        let (other, remain) = InfixIndex::deserialize_option(true, &bytes);
+        assert!(remain.is_empty());
+        let other = other.unwrap();
+        assert_eq!(index.nodes, other.nodes);
+        assert_eq!(index.begins, other.begins);
+        assert_eq!(index.ends, other.ends);
+        assert_eq!(index.values, other.values);
+    }
+}
@@ -0,0 +1,291 @@
+//! Word lattice of dictionary entries for morphological analysis.
+//!
+//! A [`Lattice`] collects all occurrences of keys in a sentence as edges,
+//! indexed by both their starting and ending positions in characters,
+//! which is the input of the Viterbi search in morphological analyzers.
+//! The buffers are reused when building lattices for successive sentences.
+//!
+//! # Examples
+//!
+//! ```
+//! use crawdad::{Lattice, Trie};
+//!
+//! let keys = vec!["東京", "東京都", "京都", "都", "に"];
+//! let trie = Trie::from_keys(&keys).unwrap();
+//!
+//! let mut lattice = Lattice::new();
+//! lattice.build_with_trie("東京都に", &trie);
+//!
+//! let starting: Vec<_> = lattice.edges_starting_at(0).iter().map(|e| e.value()).collect();
+//! assert_eq!(starting, vec![0, 1]);
+//!
+//! let ending: Vec<_> = lattice.edges_ending_at(3).map(|e| e.value()).collect();
+//! assert_eq!(ending, vec![1, 2, 3]);
+//!
+//! // Reuses the buffers for the next sentence.
+//! lattice.build_with_trie("京都に", &trie);
+//! assert_eq!(lattice.len_chars(), 3);
+//! assert_eq!(lattice.num_edges(), 3);
+//! ```
+use crate::{Match, MpTrie, Trie};
+
+use alloc::vec::Vec;
+
+use core::slice;
+
+/// Word lattice whose edges are occurrences of keys in a sentence.
+///
+/// Each edge is a [`Match`] providing its value and positions both in bytes and in characters.
+#[derive(Default, Clone, Debug)]
+pub struct Lattice {
+    // Edges ordered by their starting positions and then their ending positions.
+    edges: Vec<Match>,
+    // Edges starting at the i-th character are edges[start_offsets[i]..start_offsets[i + 1]].
+    start_offsets: Vec<usize>,
+    // Indices of edges ordered by their ending positions and then their starting positions.
+    end_edges: Vec<usize>,
+    // Edges ending at the i-th character are end_edges[end_offsets[i]..end_offsets[i + 1]].
+    end_offsets: Vec<usize>,
+    // Byte positions of characters, followed by the length of the sentence.
+    byte_positions: Vec<usize>,
+}
+
+impl Lattice {
+    /// Creates a new empty [`Lattice`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the lattice of a sentence with keys in a [`Trie`].
+    ///
+    /// # Arguments
+    ///
+    /// - `sentence`: Input sentence.
+    /// - `trie`: Dictionary.
+    pub fn build_with_trie(&mut self, sentence: &str, trie: &Trie) {
+        self.build_from_matches(sentence, trie.find_overlapping_iter(sentence));
+    }
+
+    /// Builds the lattice of a sentence with keys in an [`MpTrie`].
+    ///
+    /// # Arguments
+    ///
+    /// - `sentence`: Input sentence.
+    /// - `trie`: Dictionary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Lattice, MpTrie};
+    ///
+    /// let keys = vec!["東京", "東京都", "京都", "都", "に"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let mut lattice = Lattice::new();
+    /// lattice.build_with_mptrie("東京都に", &trie);
+    ///
+    /// let edges: Vec<_> = lattice
+    ///     .edges()
+    ///     .iter()
+    ///     .map(|e| (e.value(), e.range_in_chars()))
+    ///     .collect();
+    /// assert_eq!(edges, vec![(0, 0..2), (1, 0..3), (2, 1..3), (3, 2..3), (4, 3..4)]);
+    /// ```
+    pub fn build_with_mptrie(&mut self, sentence: &str, trie: &MpTrie) {
+        self.build_from_matches(sentence, trie.find_overlapping_iter(sentence));
+    }
+
+    // The matches must be ordered by their starting positions and then their lengths.
+    fn build_from_matches<I>(&mut self, sentence: &str, matches: I)
+    where
+        I: Iterator<Item = Match>,
+    {
+        self.byte_positions.clear();
+        self.byte_positions
+            .extend(sentence.char_indices().map(|(i, _)| i));
+        self.byte_positions.push(sentence.len());
+        let len_chars = self.len_chars();
+
+        self.edges.clear();
+        self.edges.extend(matches);
+
+        // Counts the edges at each position and accumulates the counts into offsets.
+        self.start_offsets.clear();
+        self.start_offsets.resize(len_chars + 2, 0);
+        self.end_offsets.clear();
+        self.end_offsets.resize(len_chars + 2, 0);
+        for e in &self.edges {
+            self.start_offsets[e.start_in_chars() + 1] += 1;
+            self.end_offsets[e.end_in_chars() + 1] += 1;
+        }
+        for i in 1..self.start_offsets.len() {
+            self.start_offsets[i] += self.start_offsets[i - 1];
+            self.end_offsets[i] += self.end_offsets[i - 1];
+        }
+
+        // Distributes the edges to the buckets of their ending positions,
+        // using the offsets as cursors. Since the edges are visited in the order of
+        // their starting positions, the edges in each bucket are also ordered by them.
+        self.end_edges.clear();
+        self.end_edges.resize(self.edges.len(), 0);
+        for (i, e) in self.edges.iter().enumerate() {
+            let cursor = &mut self.end_offsets[e.end_in_chars()];
+            self.end_edges[*cursor] = i;
+            *cursor += 1;
+        }
+        // Each cursor now points to the beginning of the next bucket.
+        self.end_offsets.copy_within(..len_chars + 1, 1);
+        self.end_offsets[0] = 0;
+    }
+
+    /// Returns the number of characters in the sentence.
+    pub fn len_chars(&self) -> usize {
+        self.byte_positions.len().saturating_sub(1)
+    }
+
+    /// Returns the number of edges.
+    pub fn num_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Returns all the edges ordered by their starting positions and then their ending positions.
+    pub fn edges(&self) -> &[Match] {
+        &self.edges
+    }
+
+    /// Returns the edges starting at the `pos`-th character, ordered by their ending positions.
+    ///
+    /// # Arguments
+    ///
+    /// - `pos`: Position in characters.
+    pub fn edges_starting_at(&self, pos: usize) -> &[Match] {
+        match (self.start_offsets.get(pos), self.start_offsets.get(pos + 1)) {
+            (Some(&begin), Some(&end)) => &self.edges[begin..end],
+            _ => &[],
+        }
+    }
+
+    /// Returns an iterator over the edges ending at the `pos`-th character,
+    /// ordered by their starting positions.
+    ///
+    /// # Arguments
+    ///
+    /// - `pos`: Position in characters.
+    pub fn edges_ending_at(&self, pos: usize) -> EdgesEndingAt<'_> {
+        let idxs = match (self.end_offsets.get(pos), self.end_offsets.get(pos + 1)) {
+            (Some(&begin), Some(&end)) => &self.end_edges[begin..end],
+            _ => &[],
+        };
+        EdgesEndingAt {
+            edges: &self.edges,
+            idxs: idxs.iter(),
+        }
+    }
+
+    /// Returns the position in bytes of the `pos`-th character,
+    /// or the length of the sentence if `pos` is the number of characters.
+    ///
+    /// # Arguments
+    ///
+    /// - `pos`: Position in characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Lattice, Trie};
+    ///
+    /// let keys = vec!["東京", "都"];
+    /// let trie = Trie::from_keys(&keys).unwrap();
+    ///
+    /// let mut lattice = Lattice::new();
+    /// lattice.build_with_trie("東京都", &trie);
+    ///
+    /// assert_eq!(lattice.byte_position(1), Some(3));
+    /// assert_eq!(lattice.byte_position(3), Some(9));
+    /// assert_eq!(lattice.byte_position(4), None);
+    /// ```
+    pub fn byte_position(&self, pos: usize) -> Option<usize> {
+        self.byte_positions.get(pos).copied()
+    }
+}
+
+/// Iterator over the edges ending at a position, created by [`Lattice::edges_ending_at()`].
+pub struct EdgesEndingAt<'a> {
+    edges: &'a [Match],
+    idxs: slice::Iter<'a, usize>,
+}
+
+impl<'a> Iterator for EdgesEndingAt<'a> {
+    type Item = &'a Match;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.idxs.next().map(|&i| &self.edges[i])
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.idxs.size_hint()
+    }
+}
+
+impl ExactSizeIterator for EdgesEndingAt<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::String;
+
+    #[test]
+    fn test_lattice() {
+        let keys = vec!["東京", "東京都", "京", "京都", "都", "都に", "に", "住む"];
+        let trie = Trie::from_keys(&keys).unwrap();
+        let mptrie = MpTrie::from_keys(&keys).unwrap();
+
+        let mut lattice = Lattice::new();
+        let mut mplattice = Lattice::new();
+        for sentence in ["東京都に住む", "京都", "", "大阪", "東京都東京都"] {
+            lattice.build_with_trie(sentence, &trie);
+            mplattice.build_with_mptrie(sentence, &mptrie);
+
+            let chars: Vec<char> = sentence.chars().collect();
+            let mut expected = vec![];
+            for i in 0..chars.len() {
+                for j in i + 1..=chars.len() {
+                    let word: String = chars[i..j].iter().collect();
+                    if let Some(v) = keys.iter().position(|&k| k == word) {
+                        expected.push((u32::try_from(v).unwrap(), i, j));
+                    }
+                }
+            }
+
+            assert_eq!(lattice.len_chars(), chars.len());
+            assert_eq!(lattice.num_edges(), expected.len());
+            assert_eq!(lattice.edges(), mplattice.edges());
+            for e in lattice.edges() {
+                assert_eq!(lattice.byte_position(e.start_in_chars()), Some(e.start()));
+                assert_eq!(lattice.byte_position(e.end_in_chars()), Some(e.end()));
+            }
+            for pos in 0..=chars.len() + 1 {
+                let starting: Vec<_> = lattice
+                    .edges_starting_at(pos)
+                    .iter()
+                    .map(|e| (e.value(), e.start_in_chars(), e.end_in_chars()))
+                    .collect();
+                let expected_starting: Vec<_> =
+                    expected.iter().copied().filter(|e| e.1 == pos).collect();
+                assert_eq!(starting, expected_starting);
+
+                let ending: Vec<_> = lattice
+                    .edges_ending_at(pos)
+                    .map(|e| (e.value(), e.start_in_chars(), e.end_in_chars()))
+                    .collect();
+                let mut expected_ending: Vec<_> =
+                    expected.iter().copied().filter(|e| e.2 == pos).collect();
+                expected_ending.sort_unstable_by_key(|e| e.1);
+                assert_eq!(ending, expected_ending);
+            }
+        }
+    }
+}
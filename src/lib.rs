@@ -11,6 +11,13 @@
 //! - [`Trie`] is a standard trie form that often provides the fastest queries.
 //! - [`MpTrie`] is a minimal-prefix trie form that is memory-efficient for long strings.
 //!
+//! In addition, [`AcTrie`] is an Aho-Corasick automaton form built on [`Trie`]
+//! that provides linear-time pattern matching on long texts,
+//! and [`SuffixTrie`] stores keys reversed for right-to-left text processing.
+//! [`Lattice`] collects occurrences of keys in a sentence for morphological analysis.
+//!
+//! These data structures can be built with optional features through [`Builder`].
+//!
 //! # Examples
 //!
 //! ## Looking up an input key
@@ -30,7 +37,8 @@
 //! ## Finding all occurrences of keys in an input text
 //!
 //! To search for all occurrences of registered keys in an input text,
-//! use [`Trie::common_prefix_search()`] for all starting positions in the text.
+//! use [`Trie::find_overlapping_iter()`].
+//! Each occurrence reports its positions both in bytes and in characters.
 //!
 //! ```
 //! use crawdad::Trie;
@@ -38,18 +46,15 @@
 //! let keys = vec!["世界", "世界中", "国民"];
 //! let trie = Trie::from_keys(&keys).unwrap();
 //!
-//! let haystack: Vec<char> = "国民が世界中にて".chars().collect();
-//! let mut matches = vec![];
-//!
-//! for i in 0..haystack.len() {
-//!     for (v, j) in trie.common_prefix_search(haystack[i..].iter().copied()) {
-//!         matches.push((v, i..i + j));
-//!     }
-//! }
+//! let haystack = "国民が世界中にて";
+//! let matches: Vec<_> = trie
+//!     .find_overlapping_iter(haystack)
+//!     .map(|m| (m.value(), m.range(), m.range_in_chars()))
+//!     .collect();
 //!
 //! assert_eq!(
 //!     matches,
-//!     vec![(2, 0..2), (0, 3..5), (1, 3..6)]
+//!     vec![(2, 0..6, 0..2), (0, 9..15, 3..5), (1, 9..18, 3..6)]
 //! );
 //! ```
 //!
@@ -69,6 +74,11 @@
 //!
 //! assert_eq!(trie.io_bytes(), other.io_bytes());
 //! ```
+//!
+//! The data structure built without any option of [`Builder`] is serialized in the same layout as
+//! version 0.4.0. Otherwise, the byte sequence starts with the magic bytes `CRWD`,
+//! followed by the format version and the bitmask of the optional sections,
+//! and cannot be deserialized by version 0.4.0.
 #![deny(missing_docs)]
 #![no_std]
 
@@ -81,10 +91,19 @@ compile_error!("`alloc` feature is currently required to build this crate");
 #[macro_use]
 extern crate alloc;
 
-mod builder;
+pub mod actrie;
+pub mod automaton;
+pub mod builder;
 pub mod errors;
+mod infix;
+pub mod lattice;
 mod mapper;
 pub mod mptrie;
+pub mod normalizer;
+mod sections;
+mod siblings;
+mod sparse;
+pub mod suffixtrie;
 pub mod trie;
 mod utils;
 
@@ -96,9 +115,102 @@ pub(crate) const END_CODE: u32 = 0;
 /// Special terminator, which must not be contained in keys.
 pub const END_MARKER: char = '\u{ffff}';
 
+pub use actrie::AcTrie;
+pub use builder::Builder;
+pub use lattice::Lattice;
 pub use mptrie::MpTrie;
+pub use normalizer::Normalizer;
+pub use suffixtrie::SuffixTrie;
 pub use trie::Trie;
 
+use core::ops::Range;
+
+/// Match semantics for non-overlapping search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MatchKind {
+    /// Reports the longest occurrence among those starting at the leftmost position.
+    LeftmostLongest,
+
+    /// Reports the shortest occurrence among those starting at the leftmost position.
+    LeftmostShortest,
+
+    /// Reports the occurrence with the smallest value among those starting at the leftmost
+    /// position, i.e., values are used as priorities.
+    /// If several occurrences have the same value, the shortest one is reported.
+    LeftmostFirst,
+}
+
+/// Occurrence of a key in a haystack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Match {
+    value: u32,
+    start: usize,
+    end: usize,
+    start_in_chars: usize,
+    end_in_chars: usize,
+}
+
+impl Match {
+    #[inline(always)]
+    pub(crate) const fn new(
+        value: u32,
+        start: usize,
+        end: usize,
+        start_in_chars: usize,
+        end_in_chars: usize,
+    ) -> Self {
+        Self {
+            value,
+            start,
+            end,
+            start_in_chars,
+            end_in_chars,
+        }
+    }
+
+    /// Returns the value associated with the matched key.
+    #[inline(always)]
+    pub const fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// Returns the starting position of the match in bytes.
+    #[inline(always)]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending position of the match in bytes.
+    #[inline(always)]
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the byte range of the match.
+    #[inline(always)]
+    pub const fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the starting position of the match in characters.
+    #[inline(always)]
+    pub const fn start_in_chars(&self) -> usize {
+        self.start_in_chars
+    }
+
+    /// Returns the ending position of the match in characters.
+    #[inline(always)]
+    pub const fn end_in_chars(&self) -> usize {
+        self.end_in_chars
+    }
+
+    /// Returns the character range of the match.
+    #[inline(always)]
+    pub const fn range_in_chars(&self) -> Range<usize> {
+        self.start_in_chars..self.end_in_chars
+    }
+}
+
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 struct Node {
     base: u32,
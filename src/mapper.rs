@@ -1,3 +1,5 @@
+use crate::normalizer::Composer;
+
 use alloc::vec::Vec;
 
 use core::mem::size_of;
@@ -8,6 +10,11 @@ pub const INVALID_CODE: u32 = u32::MAX;
 pub struct CodeMapper {
     table: Vec<u32>,
     alphabet_size: u32,
+    // Reverse table from codes to characters.
+    // Aliases sharing a code with another character are not contained.
+    chars: Vec<char>,
+    // Whether kana and the following sound marks are composed before mapped.
+    composes: bool,
 }
 
 impl CodeMapper {
@@ -22,15 +29,57 @@ impl CodeMapper {
         };
 
         let mut table = vec![INVALID_CODE; freqs.len()];
+        let mut chars = Vec::with_capacity(sorted.len());
         for (i, &(c, _)) in sorted.iter().enumerate() {
             table[c] = i.try_into().unwrap();
+            chars.push(char::from_u32(c.try_into().unwrap()).unwrap());
         }
+        Self::from_parts(table, chars)
+    }
+
+    fn from_parts(table: Vec<u32>, chars: Vec<char>) -> Self {
         Self {
             table,
-            alphabet_size: sorted.len().try_into().unwrap(),
+            alphabet_size: chars.len().try_into().unwrap(),
+            chars,
+            composes: false,
         }
     }
 
+    /// Maps `alias` to the code of `c` if `c` is in the alphabet and `alias` is not.
+    pub fn add_alias(&mut self, alias: char, c: char) {
+        if self.get(alias).is_some() {
+            return;
+        }
+        if let Some(code) = self.get(c) {
+            let alias = usize::try_from(u32::from(alias)).unwrap();
+            if self.table.len() <= alias {
+                self.table.resize(alias + 1, INVALID_CODE);
+            }
+            self.table[alias] = code;
+        }
+    }
+
+    /// Enables the composition of kana and the following sound marks.
+    pub fn enable_composition(&mut self) {
+        self.composes = true;
+    }
+
+    #[inline(always)]
+    pub const fn composes(&self) -> bool {
+        self.composes
+    }
+
+    /// Returns an iterator over `chars` in which kana and the following sound marks are
+    /// composed if enabled, yielding each character with the number of the original characters.
+    #[inline(always)]
+    pub fn compose<I>(&self, chars: I) -> Composer<I::IntoIter>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        Composer::new(chars.into_iter(), self.composes)
+    }
+
     #[inline]
     pub const fn alphabet_size(&self) -> u32 {
         self.alphabet_size
@@ -44,9 +93,22 @@ impl CodeMapper {
             .filter(|&code| code != INVALID_CODE)
     }
 
+    /// Returns the character mapped to `code`.
+    #[inline(always)]
+    pub fn get_char(&self, code: u32) -> char {
+        self.chars[usize::try_from(code).unwrap()]
+    }
+
+    /// Returns the character that `c` is compared as in the order of keys,
+    /// that is, its canonical form if `c` is in the alphabet.
+    #[inline(always)]
+    pub fn canonical_char(&self, c: char) -> char {
+        self.get(c).map_or(c, |code| self.get_char(code))
+    }
+
     #[inline]
     pub fn heap_bytes(&self) -> usize {
-        self.table.len() * size_of::<u32>()
+        self.table.len() * size_of::<u32>() + self.chars.len() * size_of::<char>()
     }
 
     #[inline]
@@ -62,6 +124,8 @@ impl CodeMapper {
         dest.extend_from_slice(&self.alphabet_size.to_le_bytes());
     }
 
+    /// Deserializes the mapper, in which each code is mapped back to the smallest character
+    /// having it. It is exact unless there are aliases.
     pub fn deserialize_from_slice(mut source: &[u8]) -> (Self, &[u8]) {
         let table = {
             let len = u32::from_le_bytes(source[..4].try_into().unwrap()) as usize;
@@ -75,12 +139,44 @@ impl CodeMapper {
         };
         let alphabet_size = u32::from_le_bytes(source[..4].try_into().unwrap());
         source = &source[4..];
-        (
-            Self {
-                table,
-                alphabet_size,
-            },
-            source,
-        )
+        let mut chars = vec!['\0'; usize::try_from(alphabet_size).unwrap()];
+        for (c, &code) in table.iter().enumerate().rev() {
+            if code != INVALID_CODE {
+                chars[usize::try_from(code).unwrap()] =
+                    char::from_u32(c.try_into().unwrap()).unwrap();
+            }
+        }
+        (Self::from_parts(table, chars), source)
+    }
+
+    /// Checks if some characters share a code with others.
+    pub fn has_aliases(&self) -> bool {
+        self.table
+            .iter()
+            .filter(|&&code| code != INVALID_CODE)
+            .count()
+            != self.chars.len()
+    }
+
+    /// Returns the number of bytes to serialize the characters mapped from codes.
+    pub fn chars_io_bytes(&self) -> usize {
+        self.chars.len() * size_of::<u32>()
+    }
+
+    /// Serializes the characters mapped from codes, which cannot be derived from the table
+    /// if there are aliases.
+    pub fn serialize_chars_into_vec(&self, dest: &mut Vec<u8>) {
+        for &c in &self.chars {
+            dest.extend_from_slice(&u32::from(c).to_le_bytes());
+        }
+    }
+
+    /// Deserializes the characters mapped from codes.
+    pub fn deserialize_chars_from_slice<'a>(&mut self, mut source: &'a [u8]) -> &'a [u8] {
+        for c in &mut self.chars {
+            *c = char::from_u32(u32::from_le_bytes(source[..4].try_into().unwrap())).unwrap();
+            source = &source[4..];
+        }
+        source
     }
 }
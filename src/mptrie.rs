@@ -1,22 +1,34 @@
 //! A minimal-prefix trie form that is memory-efficient for long strings.
+use crate::automaton::Automaton;
 use crate::builder::Builder;
 use crate::errors::Result;
 use crate::mapper::CodeMapper;
-use crate::{utils, Node};
+use crate::normalizer::{self, Composer};
+use crate::sections::Sections;
+use crate::siblings::{SiblingIter, Siblings};
+use crate::utils::{self, WildcardToken};
+use crate::{Match, MatchKind, Node};
 
-use crate::END_CODE;
+use crate::{END_CODE, INVALID_IDX};
 
+use alloc::collections::BinaryHeap;
+use alloc::string::String;
 use alloc::vec::Vec;
 
+use core::cmp::Ordering;
+use core::iter;
 use core::mem;
+use core::ops::{Bound, RangeBounds};
 
 /// A minimal-prefix trie form that is memory-efficient for long strings.
 pub struct MpTrie {
     pub(crate) mapper: CodeMapper,
     pub(crate) nodes: Vec<Node>,
+    pub(crate) siblings: Siblings,
     pub(crate) tails: Vec<u8>,
     pub(crate) code_size: u8,
     pub(crate) value_size: u8,
+    pub(crate) sections: Sections,
 }
 
 impl MpTrie {
@@ -110,6 +122,8 @@ impl MpTrie {
     /// ```
     pub fn serialize_to_vec(&self) -> Vec<u8> {
         let mut dest = Vec::with_capacity(self.io_bytes());
+        self.sections
+            .serialize_header_into_vec(&self.mapper, &mut dest);
         self.mapper.serialize_into_vec(&mut dest);
         dest.extend_from_slice(&u32::try_from(self.nodes.len()).unwrap().to_le_bytes());
         for node in &self.nodes {
@@ -119,6 +133,7 @@ impl MpTrie {
         dest.extend_from_slice(&self.tails);
         dest.extend_from_slice(&[self.code_size]);
         dest.extend_from_slice(&[self.value_size]);
+        self.sections.serialize_into_vec(&self.mapper, &mut dest);
         dest
     }
 
@@ -146,7 +161,8 @@ impl MpTrie {
     /// assert_eq!(trie.io_bytes(), other.io_bytes());
     /// ```
     pub fn deserialize_from_slice(source: &[u8]) -> (Self, &[u8]) {
-        let (mapper, mut source) = CodeMapper::deserialize_from_slice(source);
+        let (mask, source) = Sections::deserialize_header_from_slice(source);
+        let (mut mapper, mut source) = CodeMapper::deserialize_from_slice(source);
         let nodes = {
             let len = u32::from_le_bytes(source[..4].try_into().unwrap()) as usize;
             source = &source[4..];
@@ -168,15 +184,19 @@ impl MpTrie {
         };
         let code_size = source[0];
         let value_size = source[1];
+        let (sections, source) = Sections::deserialize_from_slice(mask, &mut mapper, &source[2..]);
+        let siblings = Siblings::new(&nodes, &mapper, sections.sibling_links);
         (
             Self {
                 mapper,
                 nodes,
+                siblings,
                 tails,
                 code_size,
                 value_size,
+                sections,
             },
-            &source[2..],
+            source,
         )
     }
 
@@ -203,7 +223,7 @@ impl MpTrie {
         I: IntoIterator<Item = char>,
     {
         let mut node_idx = 0;
-        let mut chars = key.into_iter();
+        let mut chars = self.mapper.compose(key).map(|(c, _)| c);
 
         while !self.is_leaf(node_idx) {
             if let Some(c) = chars.next() {
@@ -261,207 +281,3160 @@ impl MpTrie {
     ///     vec![(2, 0..2), (0, 3..5), (1, 3..6)]
     /// );
     /// ```
-    pub const fn common_prefix_search<I>(&self, haystack: I) -> CommonPrefixSearchIter<I> {
+    pub const fn common_prefix_search<I>(&self, haystack: I) -> CommonPrefixSearchIter<'_, I> {
         CommonPrefixSearchIter {
-            haystack,
+            haystack: Composer::new(haystack, self.mapper.composes()),
             haystack_pos: 0,
             trie: self,
             node_idx: 0,
         }
     }
 
-    #[inline(always)]
-    fn tail_iter(&self, tail_pos: usize) -> TailIter {
-        let tail_len = usize::try_from(self.tails[tail_pos]).unwrap();
-        TailIter {
-            trie: self,
-            pos: tail_pos + 1,
-            len: tail_len,
-        }
+    /// Returns an iterator for finding all occurrences of keys in an input haystack.
+    ///
+    /// The iterator reports [`Match`]es ordered by their starting positions,
+    /// and occurrences starting at the same position are ordered by their lengths.
+    /// Each [`Match`] provides its positions both in bytes and in characters.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let haystack = "国民が世界中にて";
+    /// let mut iter = trie.find_overlapping_iter(haystack);
+    ///
+    /// let m = iter.next().unwrap();
+    /// assert_eq!(m.value(), 2);
+    /// assert_eq!(&haystack[m.range()], "国民");
+    /// assert_eq!(m.range_in_chars(), 0..2);
+    ///
+    /// let m = iter.next().unwrap();
+    /// assert_eq!(m.value(), 0);
+    /// assert_eq!(&haystack[m.range()], "世界");
+    /// assert_eq!(m.range_in_chars(), 3..5);
+    ///
+    /// let m = iter.next().unwrap();
+    /// assert_eq!(m.value(), 1);
+    /// assert_eq!(&haystack[m.range()], "世界中");
+    /// assert_eq!(m.range_in_chars(), 3..6);
+    ///
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub const fn find_overlapping_iter<'h>(
+        &self,
+        haystack: &'h str,
+    ) -> FindOverlappingIter<'_, 'h> {
+        FindOverlappingIter {
+            haystack,
+            start: 0,
+            start_in_chars: 0,
+            pos: 0,
+            pos_in_chars: 0,
+            trie: self,
+            node_idx: 0,
+        }
+    }
+
+    /// Returns an iterator for finding non-overlapping occurrences of keys in an input haystack.
+    ///
+    /// The search starts at the beginning of the haystack. At each position, an occurrence
+    /// starting there is chosen according to `match_kind`, and the search resumes at its end.
+    /// If no occurrence starts at the position, the search moves to the next character.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    /// - `match_kind`: Semantics to choose an occurrence among those starting at the same position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{MatchKind, MpTrie};
+    ///
+    /// let records = vec![("世界", 1), ("世界中", 2), ("中国", 0), ("国民", 3)];
+    /// let trie = MpTrie::from_records(records).unwrap();
+    ///
+    /// let haystack = "世界中国民";
+    ///
+    /// let matches: Vec<_> = trie
+    ///     .find_iter(haystack, MatchKind::LeftmostLongest)
+    ///     .map(|m| &haystack[m.range()])
+    ///     .collect();
+    /// assert_eq!(matches, vec!["世界中", "国民"]);
+    ///
+    /// let matches: Vec<_> = trie
+    ///     .find_iter(haystack, MatchKind::LeftmostShortest)
+    ///     .map(|m| &haystack[m.range()])
+    ///     .collect();
+    /// assert_eq!(matches, vec!["世界", "中国"]);
+    /// ```
+    pub const fn find_iter<'h>(
+        &self,
+        haystack: &'h str,
+        match_kind: MatchKind,
+    ) -> FindIter<'_, 'h> {
+        FindIter {
+            haystack,
+            start: 0,
+            start_in_chars: 0,
+            trie: self,
+            match_kind,
+        }
+    }
+
+    /// Returns the longest key that is a prefix of an input haystack.
+    ///
+    /// The result consists of the value associated with the key and its length in characters.
+    /// It is equivalent to the last occurrence reported by [`Self::common_prefix_search()`],
+    /// but no intermediate occurrences are reported.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// assert_eq!(trie.longest_prefix_match("世界中にて".chars()), Some((1, 3)));
+    /// assert_eq!(trie.longest_prefix_match("世界が".chars()), Some((0, 2)));
+    /// assert_eq!(trie.longest_prefix_match("国際".chars()), None);
+    /// ```
+    pub fn longest_prefix_match<I>(&self, haystack: I) -> Option<(u32, usize)>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut node_idx = 0;
+        let mut longest = None;
+        let mut haystack = self.mapper.compose(haystack);
+        let mut haystack_pos = 0;
+        while let Some((c, n)) = haystack.next() {
+            if let Some(child_idx) = self
+                .mapper
+                .get(c)
+                .and_then(|mc| self.get_child_idx(node_idx, mc))
+            {
+                node_idx = child_idx;
+            } else {
+                break;
+            }
+            haystack_pos += n;
+            if self.is_leaf(node_idx) {
+                // The tail comparison can fail part-way through,
+                // and then the previous occurrence is the longest one.
+                let tail_pos = usize::try_from(self.get_value(node_idx)).unwrap();
+                let mut tail_iter = self.tail_iter(tail_pos);
+                for tc in tail_iter.by_ref() {
+                    match haystack.next() {
+                        Some((c, n)) if self.mapper.get(c) == Some(tc) => haystack_pos += n,
+                        _ => return longest.map(|(leaf_idx, len)| (self.get_value(leaf_idx), len)),
+                    }
+                }
+                return Some((tail_iter.value(), haystack_pos));
+            } else if self.has_leaf(node_idx) {
+                longest = Some((self.get_leaf_idx(node_idx), haystack_pos));
+            }
+        }
+        longest.map(|(leaf_idx, len)| (self.get_value(leaf_idx), len))
+    }
+
+    /// Returns a cursor at the root for traversing the trie character by character.
+    ///
+    /// Since characters are given one by one, kana and the following sound marks are not
+    /// composed even if the normalizer does so. Use [`Self::normalize()`] to compose them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let cursor = trie.cursor();
+    /// let cursor = cursor.step('世').unwrap();
+    /// assert_eq!(cursor.value(), None);
+    /// assert!(cursor.has_children());
+    ///
+    /// let cursor = cursor.step('界').unwrap();
+    /// assert_eq!(cursor.value(), Some(0));
+    /// assert!(cursor.has_children());
+    ///
+    /// let cursor = cursor.step('中').unwrap();
+    /// assert_eq!(cursor.value(), Some(1));
+    /// assert!(!cursor.has_children());
+    /// assert_eq!(cursor.depth(), 3);
+    ///
+    /// assert!(cursor.step('に').is_none());
+    /// ```
+    pub const fn cursor(&self) -> Cursor<'_> {
+        Cursor {
+            trie: self,
+            node_idx: 0,
+            tail_pos: 0,
+            tail_len: 0,
+            depth: 0,
+        }
+    }
+
+    /// Returns an iterator for predictive search.
+    ///
+    /// The iterator reports all keys starting with an input prefix and their associated values
+    /// in the lexicographical order.
+    ///
+    /// # Arguments
+    ///
+    /// - `prefix`: Search prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let results: Vec<_> = trie.predictive_search("世".chars()).collect();
+    /// assert_eq!(
+    ///     results,
+    ///     vec![
+    ///         ("世界".to_string(), 0),
+    ///         ("世界中".to_string(), 1),
+    ///         ("世論調査".to_string(), 2),
+    ///     ]
+    /// );
+    /// ```
+    pub fn predictive_search<I>(&self, prefix: I) -> PredictiveSearchIter<'_>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut key = String::new();
+        let mut node_idx = 0;
+        let mut stack = vec![];
+        let mut chars = self.mapper.compose(prefix).map(|(c, _)| c);
+
+        while !self.is_leaf(node_idx) {
+            if let Some(c) = chars.next() {
+                match self
+                    .mapper
+                    .get(c)
+                    .and_then(|mc| self.get_child_idx(node_idx, mc))
+                {
+                    Some(child_idx) => node_idx = child_idx,
+                    None => return PredictiveSearchIter::new(self, key, stack, node_idx),
+                }
+                key.push(self.get_label(node_idx));
+            } else {
+                stack.push((node_idx, key.len()));
+                return PredictiveSearchIter::new(self, key, stack, node_idx);
+            }
+        }
+
+        // The rest of the prefix must be a prefix of the tail.
+        let tail_pos = usize::try_from(self.get_value(node_idx)).unwrap();
+        let mut tail_iter = self.tail_iter(tail_pos);
+        for c in chars {
+            let mc = self.mapper.get(c);
+            if tail_iter.next().filter(|&tc| Some(tc) == mc).is_none() {
+                return PredictiveSearchIter::new(self, key, stack, node_idx);
+            }
+        }
+        stack.push((node_idx, key.len()));
+        PredictiveSearchIter::new(self, key, stack, node_idx)
+    }
+
+    /// Returns an iterator over all the keys and their associated values
+    /// in the lexicographical order.
+    ///
+    /// It is equivalent to predictive search with the empty prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界中", "世界", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let results: Vec<_> = trie.iter().collect();
+    /// assert_eq!(
+    ///     results,
+    ///     vec![
+    ///         ("世界".to_string(), 1),
+    ///         ("世界中".to_string(), 0),
+    ///         ("国民".to_string(), 2),
+    ///     ]
+    /// );
+    /// ```
+    pub fn iter(&self) -> PredictiveSearchIter<'_> {
+        self.predictive_search(core::iter::empty())
+    }
+
+    /// Returns an iterator over the keys in a range and their associated values
+    /// in the lexicographical order.
+    ///
+    /// Keys are compared in the code-point order of their characters,
+    /// not in the order of the internal character codes.
+    ///
+    /// # Arguments
+    ///
+    /// - `range`: Range of keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let results: Vec<_> = trie.range("世界".."世論").collect();
+    /// assert_eq!(
+    ///     results,
+    ///     vec![("世界".to_string(), 0), ("世界中".to_string(), 1)]
+    /// );
+    ///
+    /// let results: Vec<_> = trie.range("世界中"..).collect();
+    /// assert_eq!(
+    ///     results,
+    ///     vec![
+    ///         ("世界中".to_string(), 1),
+    ///         ("世論調査".to_string(), 2),
+    ///         ("国民".to_string(), 3),
+    ///     ]
+    /// );
+    /// ```
+    pub fn range<'a, R>(&self, range: R) -> RangeIter<'_>
+    where
+        R: RangeBounds<&'a str>,
+    {
+        let inner = match range.start_bound() {
+            Bound::Included(lo) => self.seek(lo.chars(), true),
+            Bound::Excluded(lo) => self.seek(lo.chars(), false),
+            Bound::Unbounded => self.iter(),
+        };
+        let canonicalize = |hi: &str| -> String { self.normalize(hi.chars()).collect() };
+        let end = match range.end_bound() {
+            Bound::Included(hi) => Bound::Included(canonicalize(hi)),
+            Bound::Excluded(hi) => Bound::Excluded(canonicalize(hi)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        RangeIter { inner, end }
+    }
+
+    /// Returns the smallest key not less than an input key and its associated value if exists.
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// assert_eq!(trie.lower_bound("世界".chars()), Some(("世界".to_string(), 0)));
+    /// assert_eq!(trie.lower_bound("世間".chars()), Some(("国民".to_string(), 3)));
+    /// assert_eq!(trie.lower_bound("日本".chars()), None);
+    /// ```
+    pub fn lower_bound<I>(&self, key: I) -> Option<(String, u32)>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.seek(key, true).next()
+    }
+
+    /// Returns the smallest key greater than an input key and its associated value if exists.
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// assert_eq!(trie.successor("世界".chars()), Some(("世界中".to_string(), 1)));
+    /// assert_eq!(trie.successor("世界中".chars()), Some(("世論調査".to_string(), 2)));
+    /// assert_eq!(trie.successor("国民".chars()), None);
+    /// ```
+    pub fn successor<I>(&self, key: I) -> Option<(String, u32)>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.seek(key, false).next()
+    }
+
+    /// Returns the greatest key less than an input key and its associated value if exists.
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// assert_eq!(trie.predecessor("世界中".chars()), Some(("世界".to_string(), 0)));
+    /// assert_eq!(trie.predecessor("世間".chars()), Some(("世論調査".to_string(), 2)));
+    /// assert_eq!(trie.predecessor("世界".chars()), None);
+    /// ```
+    pub fn predecessor<I>(&self, key: I) -> Option<(String, u32)>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        // The leaf of the greatest key less than `key` among those found so far.
+        let mut leaf_idx = None;
+        let mut node_idx = 0;
+        let mut chars = self.mapper.compose(key).map(|(c, _)| c);
+        while let Some(c) = chars.next() {
+            if self.is_leaf(node_idx) {
+                if self.cmp_tail(node_idx, iter::once(c).chain(chars)) == Ordering::Less {
+                    leaf_idx = Some(node_idx);
+                }
+                break;
+            }
+            let c = self.mapper.canonical_char(c);
+            // Keys in the subtrees of smaller siblings are greater than the key of the node.
+            if let Some(child_idx) = self
+                .children(node_idx)
+                .take_while(|&child_idx| self.get_label(child_idx) < c)
+                .last()
+            {
+                leaf_idx = Some(self.last_leaf(child_idx));
+            } else if self.has_leaf(node_idx) {
+                leaf_idx = Some(self.get_leaf_idx(node_idx));
+            }
+            match self
+                .mapper
+                .get(c)
+                .and_then(|mc| self.get_child_idx(node_idx, mc))
+            {
+                Some(child_idx) => node_idx = child_idx,
+                None => break,
+            }
+        }
+        leaf_idx.map(|leaf_idx| {
+            (
+                self.restore_key_from_leaf(leaf_idx),
+                self.get_leaf_value(leaf_idx),
+            )
+        })
+    }
+
+    /// Returns an iterator over the keys greater than `key`, or equal to it if `inclusive`,
+    /// in the lexicographical order.
+    fn seek<I>(&self, key: I, inclusive: bool) -> PredictiveSearchIter<'_>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let mut prefix = String::new();
+        let mut node_idx = 0;
+        let mut stack = vec![];
+        let mut chars = self.mapper.compose(key).map(|(c, _)| c);
+        while let Some(c) = chars.next() {
+            if self.is_leaf(node_idx) {
+                let ord = self.cmp_tail(node_idx, iter::once(c).chain(chars));
+                if ord == Ordering::Greater || inclusive && ord == Ordering::Equal {
+                    stack.push((node_idx, prefix.len()));
+                }
+                return PredictiveSearchIter::new(self, prefix, stack, node_idx);
+            }
+            let c = self.mapper.canonical_char(c);
+            // Greater siblings are pushed first so that they are popped after the matched child.
+            let num_items = stack.len();
+            let key_len = prefix.len();
+            stack.extend(
+                self.children(node_idx)
+                    .filter(|&child_idx| self.get_label(child_idx) > c)
+                    .map(|child_idx| (child_idx, key_len)),
+            );
+            stack[num_items..].reverse();
+            match self
+                .mapper
+                .get(c)
+                .and_then(|mc| self.get_child_idx(node_idx, mc))
+            {
+                Some(child_idx) => node_idx = child_idx,
+                None => return PredictiveSearchIter::new(self, prefix, stack, INVALID_IDX),
+            }
+            prefix.push(c);
+        }
+        if self.is_leaf(node_idx) {
+            // The key of the leaf is `key` itself if and only if the tail is empty.
+            let ord = self.cmp_tail(node_idx, iter::empty());
+            if ord == Ordering::Greater || inclusive {
+                stack.push((node_idx, prefix.len()));
+            }
+        } else if inclusive {
+            stack.push((node_idx, prefix.len()));
+        } else {
+            // Skips the key itself but visits its extensions.
+            let num_items = stack.len();
+            let key_len = prefix.len();
+            stack.extend(
+                self.children(node_idx)
+                    .map(|child_idx| (child_idx, key_len)),
+            );
+            stack[num_items..].reverse();
+        }
+        PredictiveSearchIter::new(self, prefix, stack, node_idx)
+    }
+
+    /// Calls a closure on all the keys and their associated values
+    /// in the lexicographical order.
+    ///
+    /// Unlike [`Self::iter()`], the closure receives a key stored in a single buffer reused
+    /// during the traversal, so no allocation is performed for each key.
+    ///
+    /// # Arguments
+    ///
+    /// - `f`: Closure called with a key and its value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let mut num_chars = 0;
+    /// trie.for_each_entry(|key, _| num_chars += key.chars().count());
+    /// assert_eq!(num_chars, 7);
+    /// ```
+    pub fn for_each_entry<F>(&self, mut f: F)
+    where
+        F: FnMut(&str, u32),
+    {
+        let mut iter = self.iter();
+        while let Some(value) = iter.advance() {
+            f(&iter.key, value);
+        }
+    }
+
+    /// Returns the occurrence starting at `start` chosen according to `match_kind`.
+    #[inline(always)]
+    fn find_at(
+        &self,
+        haystack: &str,
+        start: usize,
+        start_in_chars: usize,
+        match_kind: MatchKind,
+    ) -> Option<Match> {
+        let mut node_idx = 0;
+        let mut pos = start;
+        let mut pos_in_chars = start_in_chars;
+        let mut found: Option<Match> = None;
+        let composes = self.mapper.composes();
+        while let Some((c, len, n)) = normalizer::compose_first(&haystack[pos..], composes) {
+            node_idx = match self
+                .mapper
+                .get(c)
+                .and_then(|mc| self.get_child_idx(node_idx, mc))
+            {
+                Some(child_idx) => child_idx,
+                None => break,
+            };
+            pos += len;
+            pos_in_chars += n;
+            let value = if self.is_leaf(node_idx) {
+                let tail_pos = usize::try_from(self.get_value(node_idx)).unwrap();
+                let mut tail_iter = self.tail_iter(tail_pos);
+                for tc in tail_iter.by_ref() {
+                    match normalizer::compose_first(&haystack[pos..], composes) {
+                        Some((c, len, n)) if self.mapper.get(c) == Some(tc) => {
+                            pos += len;
+                            pos_in_chars += n;
+                        }
+                        _ => return found,
+                    }
+                }
+                tail_iter.value()
+            } else if self.has_leaf(node_idx) {
+                self.get_value(self.get_leaf_idx(node_idx))
+            } else {
+                continue;
+            };
+            let m = Match::new(value, start, pos, start_in_chars, pos_in_chars);
+            match match_kind {
+                MatchKind::LeftmostLongest => found = Some(m),
+                MatchKind::LeftmostShortest => return Some(m),
+                MatchKind::LeftmostFirst => {
+                    if found.map_or(true, |f| m.value() < f.value()) {
+                        found = Some(m);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Restores the key associated with an input value.
+    ///
+    /// It is available only when the reverse index is enabled with
+    /// [`Builder::reverse_index()`](crate::Builder::reverse_index).
+    ///
+    /// # Arguments
+    ///
+    /// - `value`: Value associated with the key.
+    ///
+    /// # Returns
+    ///
+    /// The key if the reverse index is enabled and `value` is associated with a key,
+    /// or [`None`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let keys = vec!["世界中", "世界", "国民"];
+    /// let trie = Builder::new()
+    ///     .reverse_index()
+    ///     .minimal_prefix()
+    ///     .build_from_keys(&keys)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.restore_key(0), Some("世界中".to_string()));
+    /// assert_eq!(trie.restore_key(1), Some("世界".to_string()));
+    /// assert_eq!(trie.restore_key(3), None);
+    /// ```
+    pub fn restore_key(&self, value: u32) -> Option<String> {
+        let leaf_idx = *self
+            .sections
+            .leaf_idxs
+            .as_ref()?
+            .get(usize::try_from(value).ok()?)?;
+        Some(self.restore_key_from_leaf(leaf_idx))
+    }
+
+    /// Returns the values of all keys containing an input pattern.
+    ///
+    /// # Arguments
+    ///
+    /// - `pattern`: Search pattern.
+    ///
+    /// # Returns
+    ///
+    /// The sorted distinct values if the infix index is enabled, or [`None`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .infix_index()
+    ///     .build_from_keys(&keys)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.contains_search("調査".chars()), Some(vec![2, 3]));
+    /// assert_eq!(trie.contains_search("界".chars()), Some(vec![0, 1]));
+    /// assert_eq!(trie.contains_search("国".chars()), Some(vec![]));
+    /// ```
+    pub fn contains_search<I>(&self, pattern: I) -> Option<Vec<u32>>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let pattern = self.mapper.compose(pattern).map(|(c, _)| c);
+        Some(self.sections.infix.as_ref()?.contains_search(pattern))
+    }
+
+    /// Returns the list of values associated with an input key if exists.
+    ///
+    /// It is available only when the multi-value mode is enabled with
+    /// [`Builder::multi_values()`](crate::Builder::multi_values).
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("はし", 10), ("はしら", 20), ("はし", 30), ("はし", 40)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .multi_values()
+    ///     .build_from_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.exact_match_values("はし".chars()), Some(&[10, 30, 40][..]));
+    /// assert_eq!(trie.exact_match_values("はしら".chars()), Some(&[20][..]));
+    /// assert_eq!(trie.exact_match_values("は".chars()), None);
+    /// ```
+    pub fn exact_match_values<I>(&self, key: I) -> Option<&[u32]>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.values(self.exact_match(key)?)
+    }
+
+    /// Returns the list of values with an input id if exists.
+    ///
+    /// In the multi-value mode enabled with
+    /// [`Builder::multi_values()`](crate::Builder::multi_values), the ids are associated with keys
+    /// and reported by search methods such as [`Self::common_prefix_search()`].
+    ///
+    /// # Arguments
+    ///
+    /// - `id`: Id of the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("はし", 10), ("はしら", 20), ("はし", 30), ("はし", 40)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .multi_values()
+    ///     .build_from_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// let results: Vec<_> = trie
+    ///     .common_prefix_search("はしらに".chars())
+    ///     .map(|(id, len)| (trie.values(id).unwrap(), len))
+    ///     .collect();
+    /// assert_eq!(results, vec![(&[10, 30, 40][..], 2), (&[20][..], 3)]);
+    /// ```
+    pub fn values(&self, id: u32) -> Option<&[u32]> {
+        let value_offsets = self.sections.value_offsets.as_ref()?;
+        let id = usize::try_from(id).ok()?;
+        let begin = usize::try_from(*value_offsets.get(id)?).unwrap();
+        let end = usize::try_from(*value_offsets.get(id + 1)?).unwrap();
+        Some(&self.sections.value_lists.as_ref()?[begin..end])
+    }
+
+    /// Returns the payload associated with an input key if exists.
+    ///
+    /// It is available only when the data structure is built with
+    /// [`Builder::build_from_payloads()`](crate::Builder::build_from_payloads).
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界", [1, 2]), ("世界中", [3, 4]), ("国民", [5, 6])];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .build_from_payloads(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.exact_match_payload("世界中".chars()), Some(&[3, 4][..]));
+    /// assert_eq!(trie.exact_match_payload("日本".chars()), None);
+    /// ```
+    pub fn exact_match_payload<I>(&self, key: I) -> Option<&[u8]>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.payload(self.exact_match(key)?)
+    }
+
+    /// Returns the payload with an input value if exists.
+    ///
+    /// It is available only when the data structure is built with
+    /// [`Builder::build_from_payloads()`](crate::Builder::build_from_payloads).
+    ///
+    /// # Arguments
+    ///
+    /// - `value`: Value associated with a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界", "せかい"), ("世界中", "せかいじゅう"), ("国民", "こくみん")];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .build_from_payloads(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// let results: Vec<_> = trie
+    ///     .common_prefix_search("世界中で".chars())
+    ///     .map(|(v, _)| core::str::from_utf8(trie.payload(v).unwrap()).unwrap())
+    ///     .collect();
+    /// assert_eq!(results, vec!["せかい", "せかいじゅう"]);
+    /// ```
+    pub fn payload(&self, value: u32) -> Option<&[u8]> {
+        let payload_offsets = self.sections.payload_offsets.as_ref()?;
+        let value = usize::try_from(value).ok()?;
+        let begin = usize::try_from(*payload_offsets.get(value)?).unwrap();
+        let end = usize::try_from(*payload_offsets.get(value + 1)?).unwrap();
+        Some(&self.sections.payloads.as_ref()?[begin..end])
+    }
+
+    /// Returns the wide value with an input value if exists.
+    ///
+    /// It is available only when the data structure is built with
+    /// [`Builder::build_from_wide_records()`](crate::Builder::build_from_wide_records).
+    ///
+    /// # Arguments
+    ///
+    /// - `value`: Value associated with a key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界", 1 << 40), ("世界中", u64::MAX), ("国民", 0)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .build_from_wide_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// let value = trie.exact_match("世界".chars()).unwrap();
+    /// assert_eq!(trie.wide_value(value), Some(1 << 40));
+    /// ```
+    pub fn wide_value(&self, value: u32) -> Option<u64> {
+        self.sections
+            .wide_values
+            .as_ref()?
+            .get(usize::try_from(value).ok()?)
+            .copied()
+    }
+
+    /// Returns a wide value associated with an input key if exists.
+    ///
+    /// It is available only when the data structure is built with
+    /// [`Builder::build_from_wide_records()`](crate::Builder::build_from_wide_records).
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界", 1 << 40), ("世界中", u64::MAX), ("国民", 0)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .build_from_wide_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.exact_match_wide("世界中".chars()), Some(u64::MAX));
+    /// assert_eq!(trie.exact_match_wide("日本中".chars()), None);
+    /// ```
+    pub fn exact_match_wide<I>(&self, key: I) -> Option<u64>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.wide_value(self.exact_match(key)?)
+    }
+
+    /// Returns an iterator for common prefix search reporting wide values.
+    ///
+    /// It is the same as [`Self::common_prefix_search()`] except that the iterator reports
+    /// wide values, and is available only when the data structure is built with
+    /// [`Builder::build_from_wide_records()`](crate::Builder::build_from_wide_records).
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    ///
+    /// # Returns
+    ///
+    /// The iterator if the wide values are stored, or [`None`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界", 1 << 40), ("世界中", u64::MAX), ("国民", 0)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .build_from_wide_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// let results: Vec<_> = trie
+    ///     .common_prefix_search_wide("世界中で".chars())
+    ///     .unwrap()
+    ///     .collect();
+    /// assert_eq!(results, vec![(1 << 40, 2), (u64::MAX, 3)]);
+    /// ```
+    pub fn common_prefix_search_wide<I>(
+        &self,
+        haystack: I,
+    ) -> Option<CommonPrefixSearchWideIter<'_, I>> {
+        Some(CommonPrefixSearchWideIter {
+            inner: self.common_prefix_search(haystack),
+            wide_values: self.sections.wide_values.as_ref()?,
+        })
+    }
+
+    /// Returns the number of keys starting with an input prefix.
+    ///
+    /// It takes time linear in the length of the prefix, independently of the number of keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `prefix`: Search prefix.
+    ///
+    /// # Returns
+    ///
+    /// The number of keys if the prefix counts are enabled, or [`None`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .prefix_counts()
+    ///     .build_from_keys(&keys)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.count_prefix("世".chars()), Some(3));
+    /// assert_eq!(trie.count_prefix("世界".chars()), Some(2));
+    /// assert_eq!(trie.count_prefix("日本".chars()), Some(0));
+    /// assert_eq!(trie.count_prefix("".chars()), Some(4));
+    ///
+    /// // Prefixes ending inside tails are also counted.
+    /// assert_eq!(trie.count_prefix("世論調".chars()), Some(1));
+    /// ```
+    pub fn count_prefix<I>(&self, prefix: I) -> Option<u32>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let counts = self.sections.counts.as_ref()?;
+        let mut node_idx = 0;
+        let mut chars = self.mapper.compose(prefix).map(|(c, _)| c);
+        while !self.is_leaf(node_idx) {
+            if let Some(c) = chars.next() {
+                match self
+                    .mapper
+                    .get(c)
+                    .and_then(|mc| self.get_child_idx(node_idx, mc))
+                {
+                    Some(child_idx) => node_idx = child_idx,
+                    None => return Some(0),
+                }
+            } else {
+                return counts.get(node_idx);
+            }
+        }
+
+        // The rest of the prefix must be a prefix of the tail.
+        let tail_pos = usize::try_from(self.get_value(node_idx)).unwrap();
+        let mut tail_iter = self.tail_iter(tail_pos);
+        for c in chars {
+            let mc = self.mapper.get(c);
+            if tail_iter.next().filter(|&tc| Some(tc) == mc).is_none() {
+                return Some(0);
+            }
+        }
+        Some(1)
+    }
+
+    /// Returns the `k` heaviest keys starting with an input prefix and their associated values.
+    ///
+    /// It runs a best-first search guided by the maximum weights of subtrees,
+    /// so it visits only nodes on the paths to the reported keys and their siblings.
+    ///
+    /// # Arguments
+    ///
+    /// - `prefix`: Search prefix.
+    /// - `k`: Maximum number of keys to report.
+    ///
+    /// # Returns
+    ///
+    /// The pairs of keys and values in the descending order of weights (with ties in an
+    /// unspecified order) if the trie is built with
+    /// [`Builder::build_from_weighted_keys()`](crate::Builder::build_from_weighted_keys),
+    /// or [`None`] otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界", 5), ("世界中", 8), ("世論調査", 3), ("国民", 10)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .build_from_weighted_keys(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     trie.top_k_completions("世".chars(), 2),
+    ///     Some(vec![("世界中".to_string(), 1), ("世界".to_string(), 0)])
+    /// );
+    /// ```
+    pub fn top_k_completions<I>(&self, prefix: I, k: usize) -> Option<Vec<(String, u32)>>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let max_weights = self.sections.max_weights.as_ref()?;
+        let mut results = vec![];
+        let mut node_idx = 0;
+        let mut chars = self.mapper.compose(prefix).map(|(c, _)| c);
+        while !self.is_leaf(node_idx) {
+            if let Some(c) = chars.next() {
+                match self
+                    .mapper
+                    .get(c)
+                    .and_then(|mc| self.get_child_idx(node_idx, mc))
+                {
+                    Some(child_idx) => node_idx = child_idx,
+                    None => return Some(results),
+                }
+            } else {
+                break;
+            }
+        }
+        if self.is_leaf(node_idx) {
+            // The rest of the prefix must be a prefix of the tail.
+            let tail_pos = usize::try_from(self.get_value(node_idx)).unwrap();
+            let mut tail_iter = self.tail_iter(tail_pos);
+            for c in chars {
+                let mc = self.mapper.get(c);
+                if tail_iter.next().filter(|&tc| Some(tc) == mc).is_none() {
+                    return Some(results);
+                }
+            }
+        }
+
+        // Since the weight of a node is the maximum in its subtree,
+        // leaves are popped in the descending order of their weights.
+        let mut heap = BinaryHeap::new();
+        heap.push((max_weights[usize::try_from(node_idx).unwrap()], node_idx));
+        while results.len() < k {
+            let node_idx = match heap.pop() {
+                Some((_, node_idx)) => node_idx,
+                None => break,
+            };
+            if self.is_leaf(node_idx) {
+                results.push((
+                    self.restore_key_from_leaf(node_idx),
+                    self.get_leaf_value(node_idx),
+                ));
+                continue;
+            }
+            let leaf_idx = self.has_leaf(node_idx).then(|| self.get_leaf_idx(node_idx));
+            for child_idx in self.children(node_idx).chain(leaf_idx) {
+                heap.push((max_weights[usize::try_from(child_idx).unwrap()], child_idx));
+            }
+        }
+        Some(results)
+    }
+
+    /// Returns an iterator for fuzzy search.
+    ///
+    /// The iterator reports all keys within an input Levenshtein distance from a query,
+    /// in the lexicographical order. Each result consists of the key, its associated value,
+    /// and the distance. The distance is counted in characters.
+    ///
+    /// Subtrees are pruned as soon as no key in them can be within the distance.
+    ///
+    /// # Arguments
+    ///
+    /// - `query`: Search query.
+    /// - `max_distance`: Maximum Levenshtein distance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "国民"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let results: Vec<_> = trie.fuzzy_search("世界調査".chars(), 1).collect();
+    /// assert_eq!(results, vec![("世論調査".to_string(), 2, 1)]);
+    ///
+    /// let results: Vec<_> = trie.fuzzy_search("世界".chars(), 1).collect();
+    /// assert_eq!(
+    ///     results,
+    ///     vec![("世界".to_string(), 0, 0), ("世界中".to_string(), 1, 1)]
+    /// );
+    /// ```
+    pub fn fuzzy_search<I>(&self, query: I, max_distance: usize) -> FuzzySearchIter<'_>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let query: Vec<_> = self
+            .mapper
+            .compose(query)
+            .map(|(c, _)| self.mapper.get(c))
+            .collect();
+        let rows = (0..=query.len()).collect();
+        let mut stack = vec![];
+        stack.extend(self.children(0).map(|child_idx| (child_idx, 0, 1)));
+        stack.reverse();
+        FuzzySearchIter {
+            trie: self,
+            query,
+            max_distance,
+            key: String::new(),
+            rows,
+            stack,
+        }
+    }
+
+    /// Returns an iterator for wildcard search.
+    ///
+    /// The iterator reports all keys matching an input pattern and their associated values
+    /// in the lexicographical order. In the pattern, `?` matches any single character and `*`
+    /// matches any sequence of characters, including the empty one.
+    /// A backslash makes the following character a literal, e.g., `\?` matches `?` itself.
+    ///
+    /// All children are visited only at wildcard positions, and the other positions are
+    /// looked up directly.
+    ///
+    /// # Arguments
+    ///
+    /// - `pattern`: Search pattern.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "東京都", "東北"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let results: Vec<_> = trie.wildcard_search("東?都".chars()).collect();
+    /// assert_eq!(results, vec![("東京都".to_string(), 3)]);
+    ///
+    /// let results: Vec<_> = trie.wildcard_search("世界*".chars()).collect();
+    /// assert_eq!(
+    ///     results,
+    ///     vec![("世界".to_string(), 0), ("世界中".to_string(), 1)]
+    /// );
+    ///
+    /// let results: Vec<_> = trie.wildcard_search("*調?".chars()).collect();
+    /// assert_eq!(results, vec![("世論調査".to_string(), 2)]);
+    /// ```
+    pub fn wildcard_search<I>(&self, pattern: I) -> WildcardSearchIter<'_>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let pattern: Vec<_> = utils::parse_wildcard(self.mapper.compose(pattern).map(|(c, _)| c))
+            .into_iter()
+            .map(|token| token.map(|c| self.mapper.get(c).filter(|&mc| mc != END_CODE)))
+            .collect();
+        let mut rows = vec![false; pattern.len() + 1];
+        rows[0] = true;
+        utils::wildcard_closure(&pattern, &mut rows);
+        let mut iter = WildcardSearchIter {
+            trie: self,
+            pattern,
+            key: String::new(),
+            rows,
+            stack: vec![],
+        };
+        iter.push_children(0, 0);
+        iter
+    }
+
+    /// Returns an iterator for search constrained by an automaton.
+    ///
+    /// The iterator reports all keys accepted by an input automaton and their associated values
+    /// in the lexicographical order. Subtrees are skipped as soon as
+    /// [`Automaton::can_match()`] returns `false`.
+    ///
+    /// The automaton is fed the characters of keys in their normalized forms, so a query of
+    /// the automaton should be normalized with [`Self::normalize()`] if a normalizer is set.
+    ///
+    /// # Arguments
+    ///
+    /// - `automaton`: Automaton to be intersected with the trie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::automaton::{Automaton, Levenshtein, Subsequence};
+    /// use crawdad::MpTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+    /// let trie = MpTrie::from_keys(&keys).unwrap();
+    ///
+    /// let automaton = Subsequence::new("世調".chars());
+    /// let results: Vec<_> = trie.search_with(&automaton).collect();
+    /// assert_eq!(results, vec![("世論調査".to_string(), 2)]);
+    ///
+    /// let automaton = Levenshtein::new("統計調".chars(), 1).intersection(Subsequence::new("査".chars()));
+    /// let results: Vec<_> = trie.search_with(automaton).collect();
+    /// assert_eq!(results, vec![("統計調査".to_string(), 3)]);
+    /// ```
+    pub fn search_with<A>(&self, automaton: A) -> SearchWithIter<'_, A>
+    where
+        A: Automaton,
+    {
+        let state = automaton.start();
+        let mut iter = SearchWithIter {
+            trie: self,
+            automaton,
+            key: String::new(),
+            stack: vec![],
+        };
+        iter.push_children(0, &state);
+        iter
+    }
+
+    /// Returns an iterator over the characters of an input text in the forms stored in the trie,
+    /// that is, normalized by the normalizer and the equivalence classes given at build time.
+    ///
+    /// Characters not appearing in keys are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// - `text`: Input text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::automaton::Levenshtein;
+    /// use crawdad::{Builder, MpTrie, Normalizer};
+    ///
+    /// let keys = vec!["カニ", "カレー"];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .normalizer(Normalizer::japanese())
+    ///     .build_from_keys(&keys)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.normalize("ｶﾞﾆ".chars()).collect::<String>(), "ガニ");
+    ///
+    /// let automaton = Levenshtein::new(trie.normalize("ｶﾞﾆ".chars()), 1);
+    /// let results: Vec<_> = trie.search_with(automaton).collect();
+    /// assert_eq!(results, vec![("カニ".to_string(), 0)]);
+    /// ```
+    pub fn normalize<'a, I>(&'a self, text: I) -> impl Iterator<Item = char> + 'a
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: 'a,
+    {
+        self.mapper
+            .compose(text)
+            .map(move |(c, _)| self.mapper.canonical_char(c))
+    }
+
+    /// Returns the dense id of an input key if exists.
+    ///
+    /// Ids in `[0..n-1]` are assigned to keys in the lexicographical order independently of
+    /// the values, where `n` is the number of keys. It is available only when the dense key
+    /// ids are enabled with [`Builder::key_ids()`](crate::Builder::key_ids).
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界中", 10), ("世界", 20), ("国民", 30)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .key_ids()
+    ///     .build_from_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.key_id("世界".chars()), Some(0));
+    /// assert_eq!(trie.key_id("世界中".chars()), Some(1));
+    /// assert_eq!(trie.key_id("国民".chars()), Some(2));
+    /// assert_eq!(trie.key_id("世".chars()), None);
+    /// ```
+    pub fn key_id<I>(&self, key: I) -> Option<u32>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        let ranks = self.sections.ranks.as_ref()?;
+        let mut node_idx = 0;
+        let mut chars = self.mapper.compose(key).map(|(c, _)| c);
+
+        while !self.is_leaf(node_idx) {
+            if let Some(c) = chars.next() {
+                node_idx = self
+                    .mapper
+                    .get(c)
+                    .and_then(|mc| self.get_child_idx(node_idx, mc))?;
+            } else {
+                return self
+                    .has_leaf(node_idx)
+                    .then(|| self.get_base(node_idx) ^ END_CODE)
+                    .and_then(|leaf_idx| ranks.get(leaf_idx));
+            }
+        }
+
+        let tail_pos = usize::try_from(self.get_value(node_idx)).unwrap();
+        for tc in self.tail_iter(tail_pos) {
+            chars
+                .next()
+                .and_then(|c| self.mapper.get(c))
+                .filter(|&mc| mc == tc)?;
+        }
+
+        if chars.next().is_some() {
+            return None;
+        }
+        ranks.get(node_idx)
+    }
+
+    /// Returns the key with an input dense id if exists.
+    ///
+    /// It is the inverse of [`Self::key_id()`] and is available only when the dense key ids are
+    /// enabled with [`Builder::key_ids()`](crate::Builder::key_ids).
+    ///
+    /// # Arguments
+    ///
+    /// - `id`: Dense id of the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::{Builder, MpTrie};
+    ///
+    /// let records = vec![("世界中", 10), ("世界", 20), ("国民", 30)];
+    /// let trie = Builder::new()
+    ///     .minimal_prefix()
+    ///     .key_ids()
+    ///     .build_from_records(records)
+    ///     .unwrap()
+    ///     .release_mptrie()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trie.key_at(0), Some("世界".to_string()));
+    /// assert_eq!(trie.key_at(1), Some("世界中".to_string()));
+    /// assert_eq!(trie.key_at(3), None);
+    /// ```
+    pub fn key_at(&self, id: u32) -> Option<String> {
+        let leaf_idx = *self
+            .sections
+            .key_leaves
+            .as_ref()?
+            .get(usize::try_from(id).ok()?)?;
+        Some(self.restore_key_from_leaf(leaf_idx))
+    }
+
+    /// Restores the key of a leaf by traversing the parent links.
+    fn restore_key_from_leaf(&self, leaf_idx: u32) -> String {
+        let mut chars = vec![];
+        let mut node_idx = leaf_idx;
+        while node_idx != 0 {
+            let parent_idx = self.get_check(node_idx);
+            let mc = self.get_base(parent_idx) ^ node_idx;
+            if mc != END_CODE {
+                chars.push(self.mapper.get_char(mc));
+            }
+            node_idx = parent_idx;
+        }
+        let mut key: String = chars.iter().rev().collect();
+        // Appends the tail if the leaf is not indicated with END_CODE.
+        let parent_idx = self.get_check(leaf_idx);
+        if self.get_base(parent_idx) ^ END_CODE != leaf_idx {
+            let tail_pos = usize::try_from(self.get_value(leaf_idx)).unwrap();
+            key.extend(self.tail_iter(tail_pos).map(|tc| self.mapper.get_char(tc)));
+        }
+        key
+    }
+
+    /// Returns child indices of `node_idx` in the order of their characters,
+    /// excluding the one indicated with END_CODE.
+    #[inline(always)]
+    fn children(&self, node_idx: u32) -> SiblingIter<'_> {
+        debug_assert!(!self.is_leaf(node_idx));
+        self.siblings.children(&self.nodes, node_idx)
+    }
+
+    /// Returns the leaf of the greatest key in the subtree of `node_idx`.
+    fn last_leaf(&self, mut node_idx: u32) -> u32 {
+        while !self.is_leaf(node_idx) {
+            // The key of the node itself is less than those of its descendants.
+            node_idx = match self.children(node_idx).last() {
+                Some(child_idx) => child_idx,
+                None => return self.get_leaf_idx(node_idx),
+            };
+        }
+        node_idx
+    }
+
+    /// Compares the tail of a leaf with `rest` in the lexicographical order.
+    fn cmp_tail<I>(&self, leaf_idx: u32, rest: I) -> Ordering
+    where
+        I: Iterator<Item = char>,
+    {
+        let tail_pos = usize::try_from(self.get_value(leaf_idx)).unwrap();
+        self.tail_iter(tail_pos)
+            .map(|tc| self.mapper.get_char(tc))
+            .cmp(rest.map(|c| self.mapper.canonical_char(c)))
+    }
+
+    /// Returns the value associated with a leaf.
+    fn get_leaf_value(&self, leaf_idx: u32) -> u32 {
+        // A leaf indicated with END_CODE has the value, and the others have the tail position.
+        let parent_idx = self.get_check(leaf_idx);
+        if self.get_base(parent_idx) ^ END_CODE == leaf_idx {
+            return self.get_value(leaf_idx);
+        }
+        let tail_pos = usize::try_from(self.get_value(leaf_idx)).unwrap();
+        let mut tail_iter = self.tail_iter(tail_pos);
+        for _ in tail_iter.by_ref() {}
+        tail_iter.value()
+    }
+
+    /// Returns the character on the edge incoming to `node_idx`.
+    #[inline(always)]
+    fn get_label(&self, node_idx: u32) -> char {
+        let parent_idx = self.get_check(node_idx);
+        self.mapper.get_char(self.get_base(parent_idx) ^ node_idx)
+    }
+
+    #[inline(always)]
+    fn tail_iter(&self, tail_pos: usize) -> TailIter<'_> {
+        let tail_len = usize::from(self.tails[tail_pos]);
+        TailIter {
+            trie: self,
+            pos: tail_pos + 1,
+            len: tail_len,
+        }
+    }
+
+    #[inline(always)]
+    fn get_child_idx(&self, node_idx: u32, mc: u32) -> Option<u32> {
+        if self.is_leaf(node_idx) {
+            return None;
+        }
+        Some(self.get_base(node_idx) ^ mc)
+            .filter(|&child_idx| self.get_check(child_idx) == node_idx)
+    }
+
+    #[inline(always)]
+    fn node_ref(&self, node_idx: u32) -> &Node {
+        &self.nodes[usize::try_from(node_idx).unwrap()]
+    }
+
+    #[inline(always)]
+    fn get_base(&self, node_idx: u32) -> u32 {
+        self.node_ref(node_idx).get_base()
+    }
+
+    #[inline(always)]
+    fn get_check(&self, node_idx: u32) -> u32 {
+        self.node_ref(node_idx).get_check()
+    }
+
+    #[inline(always)]
+    fn is_leaf(&self, node_idx: u32) -> bool {
+        self.node_ref(node_idx).is_leaf()
+    }
+
+    #[inline(always)]
+    fn has_leaf(&self, node_idx: u32) -> bool {
+        self.node_ref(node_idx).has_leaf()
+    }
+
+    #[inline(always)]
+    fn get_leaf_idx(&self, node_idx: u32) -> u32 {
+        let leaf_idx = self.get_base(node_idx) ^ END_CODE;
+        debug_assert_eq!(self.get_check(leaf_idx), node_idx);
+        leaf_idx
+    }
+
+    #[inline(always)]
+    fn get_value(&self, node_idx: u32) -> u32 {
+        debug_assert!(self.is_leaf(node_idx));
+        self.node_ref(node_idx).get_base()
+    }
+
+    /// Returns the total amount of heap used by this automaton in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.mapper.heap_bytes()
+            + self.nodes.len() * mem::size_of::<Node>()
+            + self.siblings.heap_bytes()
+            + self.tails.len() * mem::size_of::<u8>()
+            + self.sections.heap_bytes()
+    }
+
+    /// Returns the total amount of bytes to serialize the data structure.
+    pub fn io_bytes(&self) -> usize {
+        self.mapper.io_bytes()
+            + self.nodes.len() * Node::io_bytes()
+            + mem::size_of::<u32>()
+            + self.tails.len() * mem::size_of::<u8>()
+            + mem::size_of::<u32>()
+            + mem::size_of::<u8>() * 2
+            + self.sections.io_bytes(&self.mapper)
+    }
+
+    /// Returns the number of reserved elements.
+    pub fn num_elems(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of vacant elements.
+    ///
+    /// # Note
+    ///
+    /// It takes `O(num_elems)` time.
+    pub fn num_vacants(&self) -> usize {
+        self.nodes.iter().filter(|nd| nd.is_vacant()).count()
+    }
+}
+
+/// Iterator for common prefix search.
+pub struct CommonPrefixSearchIter<'t, I> {
+    haystack: Composer<I>,
+    haystack_pos: usize,
+    trie: &'t MpTrie,
+    node_idx: u32,
+}
+
+impl<I> Iterator for CommonPrefixSearchIter<'_, I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = (u32, usize);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((c, n)) = self.haystack.next() {
+            let mc = self.trie.mapper.get(c)?;
+            self.node_idx = self.trie.get_child_idx(self.node_idx, mc)?;
+            self.haystack_pos += n;
+            if self.trie.is_leaf(self.node_idx) {
+                let tail_pos = usize::try_from(self.trie.get_value(self.node_idx)).unwrap();
+                let mut tail_iter = self.trie.tail_iter(tail_pos);
+                for tc in tail_iter.by_ref() {
+                    let (c, n) = self.haystack.next()?;
+                    self.trie.mapper.get(c).filter(|&mc| mc == tc)?;
+                    self.haystack_pos += n;
+                }
+                return Some((tail_iter.value(), self.haystack_pos));
+            } else if self.trie.has_leaf(self.node_idx) {
+                let leaf_idx = self.trie.get_leaf_idx(self.node_idx);
+                return Some((self.trie.get_value(leaf_idx), self.haystack_pos));
+            }
+        }
+        None
+    }
+}
+
+/// Iterator for common prefix search reporting wide values.
+pub struct CommonPrefixSearchWideIter<'t, I> {
+    inner: CommonPrefixSearchIter<'t, I>,
+    wide_values: &'t [u64],
+}
+
+impl<I> Iterator for CommonPrefixSearchWideIter<'_, I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = (u64, usize);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(value, len)| (self.wide_values[usize::try_from(value).unwrap()], len))
+    }
+}
+
+/// Iterator for finding non-overlapping occurrences of keys in a haystack.
+pub struct FindIter<'t, 'h> {
+    haystack: &'h str,
+    start: usize,
+    start_in_chars: usize,
+    trie: &'t MpTrie,
+    match_kind: MatchKind,
+}
+
+impl Iterator for FindIter<'_, '_> {
+    type Item = Match;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(c) = self.haystack[self.start..].chars().next() {
+            if let Some(m) = self.trie.find_at(
+                self.haystack,
+                self.start,
+                self.start_in_chars,
+                self.match_kind,
+            ) {
+                self.start = m.end();
+                self.start_in_chars = m.end_in_chars();
+                return Some(m);
+            }
+            self.start += c.len_utf8();
+            self.start_in_chars += 1;
+        }
+        None
+    }
+}
+
+/// Iterator for finding all occurrences of keys in a haystack.
+pub struct FindOverlappingIter<'t, 'h> {
+    haystack: &'h str,
+    // Starting position of the current search in bytes and characters.
+    start: usize,
+    start_in_chars: usize,
+    // Current position of the current search in bytes and characters.
+    pos: usize,
+    pos_in_chars: usize,
+    trie: &'t MpTrie,
+    node_idx: u32,
+}
+
+impl FindOverlappingIter<'_, '_> {
+    /// Moves the starting position forward by one character.
+    /// Returns `false` if the haystack is exhausted.
+    #[inline(always)]
+    fn restart(&mut self) -> bool {
+        if let Some(c) = self.haystack[self.start..].chars().next() {
+            self.start += c.len_utf8();
+            self.start_in_chars += 1;
+            self.pos = self.start;
+            self.pos_in_chars = self.start_in_chars;
+            self.node_idx = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Iterator for FindOverlappingIter<'_, '_> {
+    type Item = Match;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let trie = self.trie;
+            let composes = trie.mapper.composes();
+            let child = normalizer::compose_first(&self.haystack[self.pos..], composes).and_then(
+                |(c, len, n)| {
+                    trie.mapper
+                        .get(c)
+                        .and_then(|mc| trie.get_child_idx(self.node_idx, mc))
+                        .map(|child_idx| (child_idx, len, n))
+                },
+            );
+            let (child_idx, len, n) = match child {
+                Some(child) => child,
+                None => {
+                    if self.restart() {
+                        continue;
+                    }
+                    return None;
+                }
+            };
+            self.node_idx = child_idx;
+            self.pos += len;
+            self.pos_in_chars += n;
+            let value = if trie.is_leaf(self.node_idx) {
+                let tail_pos = usize::try_from(trie.get_value(self.node_idx)).unwrap();
+                let mut tail_iter = trie.tail_iter(tail_pos);
+                let mut matched = true;
+                for tc in tail_iter.by_ref() {
+                    match normalizer::compose_first(&self.haystack[self.pos..], composes) {
+                        Some((c, len, n)) if trie.mapper.get(c) == Some(tc) => {
+                            self.pos += len;
+                            self.pos_in_chars += n;
+                        }
+                        _ => {
+                            matched = false;
+                            break;
+                        }
+                    }
+                }
+                if !matched {
+                    if self.restart() {
+                        continue;
+                    }
+                    return None;
+                }
+                tail_iter.value()
+            } else if trie.has_leaf(self.node_idx) {
+                trie.get_value(trie.get_leaf_idx(self.node_idx))
+            } else {
+                continue;
+            };
+            return Some(Match::new(
+                value,
+                self.start,
+                self.pos,
+                self.start_in_chars,
+                self.pos_in_chars,
+            ));
+        }
+    }
+}
+
+/// Cursor for traversing a [`MpTrie`] character by character,
+/// created by [`MpTrie::cursor()`].
+///
+/// The cursor seamlessly steps over characters stored in tails.
+#[derive(Clone, Copy)]
+pub struct Cursor<'t> {
+    trie: &'t MpTrie,
+    node_idx: u32,
+    // Position of the next code in the tail and the number of remaining codes,
+    // which are valid only if the cursor is at a leaf.
+    tail_pos: usize,
+    tail_len: usize,
+    depth: usize,
+}
+
+impl Cursor<'_> {
+    /// Returns the cursor moved with an input character if the path exists.
+    #[inline(always)]
+    pub fn step(&self, c: char) -> Option<Self> {
+        let trie = self.trie;
+        let mc = trie.mapper.get(c).filter(|&mc| mc != END_CODE)?;
+        if trie.is_leaf(self.node_idx) {
+            if self.tail_len == 0
+                || utils::unpack_u32(&trie.tails[self.tail_pos..], trie.code_size) != mc
+            {
+                return None;
+            }
+            return Some(Self {
+                tail_pos: self.tail_pos + usize::from(trie.code_size),
+                tail_len: self.tail_len - 1,
+                depth: self.depth + 1,
+                ..*self
+            });
+        }
+        let node_idx = trie.get_child_idx(self.node_idx, mc)?;
+        let (tail_pos, tail_len) = if trie.is_leaf(node_idx) {
+            let tail_pos = usize::try_from(trie.get_value(node_idx)).unwrap();
+            (tail_pos + 1, usize::from(trie.tails[tail_pos]))
+        } else {
+            (0, 0)
+        };
+        Some(Self {
+            trie,
+            node_idx,
+            tail_pos,
+            tail_len,
+            depth: self.depth + 1,
+        })
+    }
+
+    /// Returns the value associated with the key ending at the cursor if exists.
+    #[inline(always)]
+    pub fn value(&self) -> Option<u32> {
+        let trie = self.trie;
+        if trie.is_leaf(self.node_idx) {
+            (self.tail_len == 0)
+                .then(|| utils::unpack_u32(&trie.tails[self.tail_pos..], trie.value_size))
+        } else if trie.has_leaf(self.node_idx) {
+            Some(trie.get_value(trie.get_leaf_idx(self.node_idx)))
+        } else {
+            None
+        }
+    }
+
+    /// Checks if a longer key can be reached from the cursor.
+    #[inline(always)]
+    pub fn has_children(&self) -> bool {
+        // Non-leaf nodes always have a child other than the one indicated with END_CODE.
+        !self.trie.is_leaf(self.node_idx) || self.tail_len != 0
+    }
+
+    /// Returns the number of characters stepped from the root.
+    #[inline(always)]
+    pub const fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+/// Iterator for fuzzy search.
+pub struct FuzzySearchIter<'t> {
+    trie: &'t MpTrie,
+    // Codes of the query, where `None` indicates a character not in the alphabet.
+    query: Vec<Option<u32>>,
+    max_distance: usize,
+    key: String,
+    // Rows of the dynamic programming table for the keys in `key`.
+    rows: Vec<usize>,
+    // Triples of a node index, the length of the key of its parent, and its depth.
+    stack: Vec<(u32, usize, usize)>,
+}
+
+impl FuzzySearchIter<'_> {
+    /// Computes the row at `depth` for the key extended by `mc`.
+    /// Returns `false` if no key extending it can be within the distance.
+    #[inline(always)]
+    fn update_row(&mut self, depth: usize, mc: u32) -> bool {
+        let width = self.query.len() + 1;
+        if self.rows.len() < (depth + 1) * width {
+            self.rows.resize((depth + 1) * width, 0);
+        }
+        let (prev, cur) = self.rows[(depth - 1) * width..(depth + 1) * width].split_at_mut(width);
+        utils::levenshtein_step(&self.query, prev, cur, &Some(mc)) <= self.max_distance
+    }
+
+    /// Returns the distance between the query and the key at `depth`.
+    #[inline(always)]
+    fn distance(&self, depth: usize) -> usize {
+        let width = self.query.len() + 1;
+        self.rows[depth * width + width - 1]
+    }
+}
+
+impl Iterator for FuzzySearchIter<'_> {
+    type Item = (String, u32, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let trie = self.trie;
+        while let Some((node_idx, key_len, depth)) = self.stack.pop() {
+            let mc = trie.get_base(trie.get_check(node_idx)) ^ node_idx;
+            self.key.truncate(key_len);
+            self.key.push(trie.mapper.get_char(mc));
+            if !self.update_row(depth, mc) {
+                continue;
+            }
+            if trie.is_leaf(node_idx) {
+                // Continues the computation along the tail.
+                let tail_pos = usize::try_from(trie.get_value(node_idx)).unwrap();
+                let mut tail_iter = trie.tail_iter(tail_pos);
+                let mut tail_depth = depth;
+                let mut matched = true;
+                for tc in tail_iter.by_ref() {
+                    self.key.push(trie.mapper.get_char(tc));
+                    tail_depth += 1;
+                    if !self.update_row(tail_depth, tc) {
+                        matched = false;
+                        break;
+                    }
+                }
+                let distance = self.distance(tail_depth);
+                if matched && distance <= self.max_distance {
+                    return Some((self.key.clone(), tail_iter.value(), distance));
+                }
+                continue;
+            }
+            // Children are pushed in reverse order so that they are popped in the lexicographical order.
+            let num_items = self.stack.len();
+            let key_len = self.key.len();
+            self.stack.extend(
+                trie.children(node_idx)
+                    .map(|child_idx| (child_idx, key_len, depth + 1)),
+            );
+            self.stack[num_items..].reverse();
+            if trie.has_leaf(node_idx) {
+                let distance = self.distance(depth);
+                if distance <= self.max_distance {
+                    let leaf_idx = trie.get_leaf_idx(node_idx);
+                    return Some((self.key.clone(), trie.get_value(leaf_idx), distance));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Iterator for wildcard search.
+pub struct WildcardSearchIter<'t> {
+    trie: &'t MpTrie,
+    pattern: Vec<WildcardToken<Option<u32>>>,
+    key: String,
+    // Sets of the pattern positions reached by the keys in `key`.
+    rows: Vec<bool>,
+    // Triples of a node index, the length of the key of its parent, and its depth.
+    stack: Vec<(u32, usize, usize)>,
+}
+
+impl WildcardSearchIter<'_> {
+    /// Computes the row at `depth` for the key extended by `mc`.
+    /// Returns `false` if no key extending it can match the pattern.
+    #[inline(always)]
+    fn update_row(&mut self, depth: usize, mc: u32) -> bool {
+        let width = self.pattern.len() + 1;
+        if self.rows.len() < (depth + 1) * width {
+            self.rows.resize((depth + 1) * width, false);
+        }
+        let (prev, cur) = self.rows[(depth - 1) * width..(depth + 1) * width].split_at_mut(width);
+        utils::wildcard_step(&self.pattern, prev, cur, &Some(mc))
+    }
+
+    /// Checks if the key at `depth` matches the pattern.
+    #[inline(always)]
+    fn is_match(&self, depth: usize) -> bool {
+        let width = self.pattern.len() + 1;
+        self.rows[depth * width + width - 1]
+    }
+
+    /// Pushes the children of `node_idx` at `depth` that can match the pattern.
+    fn push_children(&mut self, node_idx: u32, depth: usize) {
+        let trie = self.trie;
+        let width = self.pattern.len() + 1;
+        let row = &self.rows[depth * width..(depth + 1) * width];
+        let key_len = self.key.len();
+        let num_items = self.stack.len();
+        let is_wild = self
+            .pattern
+            .iter()
+            .zip(row)
+            .any(|(&token, &reached)| reached && !matches!(token, WildcardToken::Literal(_)));
+        if is_wild {
+            self.stack.extend(
+                trie.children(node_idx)
+                    .map(|child_idx| (child_idx, key_len, depth + 1)),
+            );
+        } else {
+            for (&token, &reached) in self.pattern.iter().zip(row) {
+                if let (WildcardToken::Literal(Some(mc)), true) = (token, reached) {
+                    if let Some(child_idx) = trie.get_child_idx(node_idx, mc) {
+                        self.stack.push((child_idx, key_len, depth + 1));
+                    }
+                }
+            }
+            self.stack[num_items..]
+                .sort_unstable_by_key(|&(child_idx, ..)| trie.get_label(child_idx));
+            utils::dedup_from(&mut self.stack, num_items);
+        }
+        // Children are pushed in reverse order so that they are popped in the lexicographical order.
+        self.stack[num_items..].reverse();
+    }
+}
+
+impl Iterator for WildcardSearchIter<'_> {
+    type Item = (String, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let trie = self.trie;
+        while let Some((node_idx, key_len, depth)) = self.stack.pop() {
+            let mc = trie.get_base(trie.get_check(node_idx)) ^ node_idx;
+            self.key.truncate(key_len);
+            self.key.push(trie.mapper.get_char(mc));
+            if !self.update_row(depth, mc) {
+                continue;
+            }
+            if trie.is_leaf(node_idx) {
+                // Continues the matching along the tail.
+                let tail_pos = usize::try_from(trie.get_value(node_idx)).unwrap();
+                let mut tail_iter = trie.tail_iter(tail_pos);
+                let mut tail_depth = depth;
+                let mut matched = true;
+                for tc in tail_iter.by_ref() {
+                    self.key.push(trie.mapper.get_char(tc));
+                    tail_depth += 1;
+                    if !self.update_row(tail_depth, tc) {
+                        matched = false;
+                        break;
+                    }
+                }
+                if matched && self.is_match(tail_depth) {
+                    return Some((self.key.clone(), tail_iter.value()));
+                }
+                continue;
+            }
+            self.push_children(node_idx, depth);
+            if trie.has_leaf(node_idx) && self.is_match(depth) {
+                let leaf_idx = trie.get_leaf_idx(node_idx);
+                return Some((self.key.clone(), trie.get_value(leaf_idx)));
+            }
+        }
+        None
+    }
+}
+
+/// Iterator for search constrained by an automaton.
+pub struct SearchWithIter<'t, A>
+where
+    A: Automaton,
+{
+    trie: &'t MpTrie,
+    automaton: A,
+    key: String,
+    // Triples of a node index, the length of the key of its parent, and the state after its label.
+    stack: Vec<(u32, usize, A::State)>,
+}
+
+impl<A> SearchWithIter<'_, A>
+where
+    A: Automaton,
+{
+    /// Pushes the children of `node_idx` whose states can reach an accepting state.
+    fn push_children(&mut self, node_idx: u32, state: &A::State) {
+        let trie = self.trie;
+        let key_len = self.key.len();
+        let num_items = self.stack.len();
+        for child_idx in trie.children(node_idx) {
+            let next = self.automaton.accept(state, trie.get_label(child_idx));
+            if self.automaton.can_match(&next) {
+                self.stack.push((child_idx, key_len, next));
+            }
+        }
+        // Children are pushed in reverse order so that they are popped in the lexicographical order.
+        self.stack[num_items..].reverse();
+    }
+}
+
+impl<A> Iterator for SearchWithIter<'_, A>
+where
+    A: Automaton,
+{
+    type Item = (String, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let trie = self.trie;
+        while let Some((node_idx, key_len, state)) = self.stack.pop() {
+            self.key.truncate(key_len);
+            self.key.push(trie.get_label(node_idx));
+            if trie.is_leaf(node_idx) {
+                // Continues the matching along the tail.
+                let tail_pos = usize::try_from(trie.get_value(node_idx)).unwrap();
+                let mut tail_iter = trie.tail_iter(tail_pos);
+                let mut state = state;
+                let mut matched = true;
+                for tc in tail_iter.by_ref() {
+                    let c = trie.mapper.get_char(tc);
+                    self.key.push(c);
+                    state = self.automaton.accept(&state, c);
+                    if !self.automaton.can_match(&state) {
+                        matched = false;
+                        break;
+                    }
+                }
+                if matched && self.automaton.is_match(&state) {
+                    return Some((self.key.clone(), tail_iter.value()));
+                }
+                continue;
+            }
+            self.push_children(node_idx, &state);
+            if trie.has_leaf(node_idx) && self.automaton.is_match(&state) {
+                let leaf_idx = trie.get_leaf_idx(node_idx);
+                return Some((self.key.clone(), trie.get_value(leaf_idx)));
+            }
+        }
+        None
+    }
+}
+
+/// Iterator for predictive search.
+pub struct PredictiveSearchIter<'t> {
+    trie: &'t MpTrie,
+    key: String,
+    // Pairs of a node index and the length of the key of its parent.
+    stack: Vec<(u32, usize)>,
+    start_idx: u32,
+}
+
+impl<'t> PredictiveSearchIter<'t> {
+    const fn new(trie: &'t MpTrie, key: String, stack: Vec<(u32, usize)>, start_idx: u32) -> Self {
+        Self {
+            trie,
+            key,
+            stack,
+            start_idx,
+        }
+    }
+}
+
+impl Iterator for PredictiveSearchIter<'_> {
+    type Item = (String, u32);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance().map(|value| (self.key.clone(), value))
+    }
+}
+
+impl PredictiveSearchIter<'_> {
+    /// Moves to the next key, stores it in `self.key`, and returns its value.
+    fn advance(&mut self) -> Option<u32> {
+        let trie = self.trie;
+        while let Some((node_idx, key_len)) = self.stack.pop() {
+            if node_idx != self.start_idx {
+                self.key.truncate(key_len);
+                self.key.push(trie.get_label(node_idx));
+            }
+            if trie.is_leaf(node_idx) {
+                let tail_pos = usize::try_from(trie.get_value(node_idx)).unwrap();
+                let mut tail_iter = trie.tail_iter(tail_pos);
+                for tc in tail_iter.by_ref() {
+                    self.key.push(trie.mapper.get_char(tc));
+                }
+                return Some(tail_iter.value());
+            }
+            // Children are pushed in reverse order so that they are popped in the lexicographical order.
+            let num_items = self.stack.len();
+            let key_len = self.key.len();
+            self.stack.extend(
+                trie.children(node_idx)
+                    .map(|child_idx| (child_idx, key_len)),
+            );
+            self.stack[num_items..].reverse();
+            if trie.has_leaf(node_idx) {
+                let leaf_idx = trie.get_leaf_idx(node_idx);
+                return Some(trie.get_value(leaf_idx));
+            }
+        }
+        None
+    }
+}
+
+/// Iterator for range search.
+pub struct RangeIter<'t> {
+    inner: PredictiveSearchIter<'t>,
+    end: Bound<String>,
+}
+
+impl Iterator for RangeIter<'_> {
+    type Item = (String, u32);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.inner.advance()?;
+        let key = &self.inner.key;
+        let in_range = match &self.end {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        };
+        if in_range {
+            Some((key.clone(), value))
+        } else {
+            // The following keys are also out of the range.
+            self.inner.stack.clear();
+            None
+        }
+    }
+}
+
+struct TailIter<'a> {
+    trie: &'a MpTrie,
+    pos: usize,
+    len: usize,
+}
+
+impl TailIter<'_> {
+    #[inline(always)]
+    fn value(&self) -> u32 {
+        utils::unpack_u32(&self.trie.tails[self.pos..], self.trie.value_size)
+    }
+}
+
+impl Iterator for TailIter<'_> {
+    type Item = u32;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len != 0 {
+            let c = utils::unpack_u32(&self.trie.tails[self.pos..], self.trie.code_size);
+            self.pos += usize::from(self.trie.code_size);
+            self.len -= 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_exact_match() {
+        let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(
+                trie.exact_match(key.chars()),
+                Some(u32::try_from(i).unwrap())
+            );
+        }
+        assert_eq!(trie.exact_match("世".chars()), None);
+        assert_eq!(trie.exact_match("世論".chars()), None);
+        assert_eq!(trie.exact_match("世界中で".chars()), None);
+        assert_eq!(trie.exact_match("統計".chars()), None);
+        assert_eq!(trie.exact_match("統計調".chars()), None);
+        assert_eq!(trie.exact_match("日本".chars()), None);
+    }
+
+    #[test]
+    fn test_common_prefix_search() {
+        let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+
+        let haystack: Vec<_> = "世界中の統計世論調査".chars().collect();
+        let mut matches = vec![];
+
+        for i in 0..haystack.len() {
+            for (v, j) in trie.common_prefix_search(haystack[i..].iter().copied()) {
+                matches.push((v, i..i + j));
+            }
+        }
+        assert_eq!(matches, vec![(0, 0..2), (1, 0..3), (2, 6..10)]);
+    }
+
+    #[test]
+    fn test_find_overlapping_iter() {
+        let keys = vec!["世界", "世界中", "世論調査", "統計調査", "a"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+
+        let haystack = "世界中aの統計世論調査a世論";
+        let matches: Vec<_> = trie
+            .find_overlapping_iter(haystack)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (0, "世界", 0..2),
+                (1, "世界中", 0..3),
+                (4, "a", 3..4),
+                (2, "世論調査", 7..11),
+                (4, "a", 11..12),
+            ]
+        );
+
+        let haystack: Vec<_> = "世界中aの統計世論調査a世論".chars().collect();
+        let mut expected = vec![];
+        for i in 0..haystack.len() {
+            for (v, j) in trie.common_prefix_search(haystack[i..].iter().copied()) {
+                expected.push((v, i..i + j));
+            }
+        }
+        let matches: Vec<_> = trie
+            .find_overlapping_iter("世界中aの統計世論調査a世論")
+            .map(|m| (m.value(), m.range_in_chars()))
+            .collect();
+        assert_eq!(matches, expected);
+
+        assert_eq!(trie.find_overlapping_iter("").next(), None);
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let records = vec![
+            ("世界", 3),
+            ("世界中", 1),
+            ("世論調査", 0),
+            ("界中", 2),
+            ("中の統計", 4),
+            ("の", 5),
+        ];
+        let trie = MpTrie::from_records(records).unwrap();
+        let haystack = "世界中の統計世論調査世論";
+
+        let matches: Vec<_> = trie
+            .find_iter(haystack, MatchKind::LeftmostLongest)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![(1, "世界中", 0..3), (5, "の", 3..4), (0, "世論調査", 6..10)]
+        );
+
+        let matches: Vec<_> = trie
+            .find_iter(haystack, MatchKind::LeftmostShortest)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (3, "世界", 0..2),
+                (4, "中の統計", 2..6),
+                (0, "世論調査", 6..10)
+            ]
+        );
+
+        let records = vec![("世界", 1), ("世界中", 2), ("世界中の統計", 0)];
+        let trie = MpTrie::from_records(records).unwrap();
+        let matches: Vec<_> = trie
+            .find_iter(haystack, MatchKind::LeftmostFirst)
+            .map(|m| (m.value(), &haystack[m.range()]))
+            .collect();
+        assert_eq!(matches, vec![(0, "世界中の統計")]);
+        let matches: Vec<_> = trie
+            .find_iter("世界中の", MatchKind::LeftmostFirst)
+            .map(|m| (m.value(), m.range_in_chars()))
+            .collect();
+        assert_eq!(matches, vec![(1, 0..2)]);
+
+        assert_eq!(trie.find_iter("", MatchKind::LeftmostLongest).next(), None);
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+
+        assert_eq!(trie.longest_prefix_match("世界中の".chars()), Some((1, 3)));
+        assert_eq!(trie.longest_prefix_match("世界の".chars()), Some((0, 2)));
+        assert_eq!(trie.longest_prefix_match("世界".chars()), Some((0, 2)));
+        assert_eq!(
+            trie.longest_prefix_match("世論調査は".chars()),
+            Some((2, 4))
+        );
+        assert_eq!(trie.longest_prefix_match("世論調".chars()), None);
+        assert_eq!(trie.longest_prefix_match("世論".chars()), None);
+        assert_eq!(trie.longest_prefix_match("統計調整".chars()), None);
+        assert_eq!(trie.longest_prefix_match("日本".chars()), None);
+        assert_eq!(trie.longest_prefix_match("".chars()), None);
+
+        let haystack: Vec<_> = "世界中の統計世論調査".chars().collect();
+        for i in 0..haystack.len() {
+            assert_eq!(
+                trie.longest_prefix_match(haystack[i..].iter().copied()),
+                trie.common_prefix_search(haystack[i..].iter().copied())
+                    .last()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let keys = vec!["世界", "世界中", "世論調査", "統計調査", "世論", "統計"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+
+        let results: Vec<_> = trie.fuzzy_search("世論調".chars(), 1).collect();
+        assert_eq!(
+            results,
+            vec![("世論".to_string(), 4, 1), ("世論調査".to_string(), 2, 1)]
+        );
+
+        let results: Vec<_> = trie.fuzzy_search("世界調査".chars(), 2).collect();
+        assert_eq!(
+            results,
+            vec![
+                ("世界".to_string(), 0, 2),
+                ("世界中".to_string(), 1, 2),
+                ("世論調査".to_string(), 2, 1),
+                ("統計調査".to_string(), 3, 2),
+            ]
+        );
+
+        let results: Vec<_> = trie.fuzzy_search("統計".chars(), 0).collect();
+        assert_eq!(results, vec![("統計".to_string(), 5, 0)]);
+
+        // Brute force
+        let query: Vec<_> = "世計調".chars().collect();
+        for k in 0..5 {
+            let mut expected = vec![];
+            for (i, key) in keys.iter().enumerate() {
+                let mut prev: Vec<_> = (0..=query.len()).collect();
+                let mut cur = vec![0; query.len() + 1];
+                for c in key.chars() {
+                    utils::levenshtein_step(&query, &prev, &mut cur, &c);
+                    core::mem::swap(&mut prev, &mut cur);
+                }
+                if prev[query.len()] <= k {
+                    expected.push((
+                        key.to_string(),
+                        u32::try_from(i).unwrap(),
+                        prev[query.len()],
+                    ));
+                }
+            }
+            expected.sort_unstable();
+            let results: Vec<_> = trie.fuzzy_search(query.iter().copied(), k).collect();
+            assert_eq!(results, expected);
+        }
+    }
+
+    #[test]
+    fn test_wildcard_search() {
+        let keys = vec![
+            "世界",
+            "世界中",
+            "世論調査",
+            "統計調査",
+            "世論",
+            "統計",
+            "東京都",
+            "京都",
+            "本当?",
+            "本当!",
+        ];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+
+        let search = |pattern: &str| {
+            trie.wildcard_search(pattern.chars())
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search("世?"), vec!["世界", "世論"]);
+        assert_eq!(search("世*"), vec!["世界", "世界中", "世論", "世論調査"]);
+        assert_eq!(search("*調査"), vec!["世論調査", "統計調査"]);
+        assert_eq!(search("*京*"), vec!["京都", "東京都"]);
+        assert_eq!(search("?京?"), vec!["東京都"]);
+        assert_eq!(search("統計調査*"), vec!["統計調査"]);
+        assert_eq!(search("統計調??"), Vec::<String>::new());
+        assert_eq!(search("世論調査"), vec!["世論調査"]);
+        assert_eq!(search("世論調"), Vec::<String>::new());
+        let mut all_keys: Vec<_> = keys.iter().map(|k| k.to_string()).collect();
+        all_keys.sort_unstable();
+        assert_eq!(search("*"), all_keys);
+        assert_eq!(search("**?**"), all_keys);
+        assert_eq!(search("日本*"), Vec::<String>::new());
+        assert_eq!(search("本当?"), vec!["本当!", "本当?"]);
+        assert_eq!(search(r"本当\?"), vec!["本当?"]);
+        assert_eq!(search(r"*\?"), vec!["本当?"]);
+        assert_eq!(search(r"本\*"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_search_with() {
+        use crate::automaton::{Automaton, CharFilter, Levenshtein, Subsequence, Wildcard};
+
+        let keys = vec![
+            "世界",
+            "世界中",
+            "世論調査",
+            "統計調査",
+            "世論",
+            "統計",
+            "東京都",
+            "京都",
+        ];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+
+        for query in ["世界", "世論調", "統計調査", "京", ""] {
+            for k in 0..3 {
+                let expected: Vec<_> = trie
+                    .fuzzy_search(query.chars(), k)
+                    .map(|(key, v, _)| (key, v))
+                    .collect();
+                let results: Vec<_> = trie
+                    .search_with(Levenshtein::new(query.chars(), k))
+                    .collect();
+                assert_eq!(results, expected);
+            }
+        }
+        for pattern in [
+            "世?",
+            "世*",
+            "*調査",
+            "*京*",
+            "?京?",
+            "統計調??",
+            "**?**",
+            r"世\?",
+            r"*\*",
+            "",
+        ] {
+            let expected: Vec<_> = trie.wildcard_search(pattern.chars()).collect();
+            let results: Vec<_> = trie.search_with(Wildcard::new(pattern.chars())).collect();
+            assert_eq!(results, expected);
+        }
+
+        let search = |automaton| {
+            trie.search_with(automaton)
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            search(Subsequence::new("調査".chars()).intersection(CharFilter::new(|c| c != '論'))),
+            vec!["統計調査"]
+        );
+    }
+
+    #[test]
+    fn test_contains_search() {
+        let keys = vec![
+            "世界",
+            "世界中",
+            "世論調査",
+            "統計調査",
+            "調査",
+            "査察",
+            "世",
+        ];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .infix_index()
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+
+        for pattern in [
+            "世",
+            "界",
+            "調査",
+            "査",
+            "査察",
+            "世論調査",
+            "論調",
+            "国",
+            "世界中の",
+        ] {
+            let expected: Vec<_> = keys
+                .iter()
+                .enumerate()
+                .filter(|(_, k)| k.contains(pattern))
+                .map(|(i, _)| u32::try_from(i).unwrap())
+                .collect();
+            assert_eq!(trie.contains_search(pattern.chars()), Some(expected));
+        }
+        assert_eq!(trie.contains_search("".chars()), Some((0..7).collect()));
+
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (other, remain) = MpTrie::deserialize_from_slice(&bytes);
+        assert!(remain.is_empty());
+        assert_eq!(other.contains_search("調査".chars()), Some(vec![2, 3, 4]));
+
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        assert_eq!(trie.contains_search("調査".chars()), None);
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let keys = vec!["ｶﾆ", "かに鍋", "ＣＲＡＢ", "ラーメン"];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .normalizer(crate::Normalizer::japanese())
+            .infix_index()
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+
+        assert_eq!(trie.exact_match("カニ".chars()), Some(0));
+        assert_eq!(trie.exact_match("ｶﾆ鍋".chars()), Some(1));
+        assert_eq!(trie.exact_match("Crab".chars()), Some(2));
+        assert_eq!(trie.exact_match("ら―めん".chars()), Some(3));
+
+        let haystack = "Ｃｒａｂとカﾆナベとらｰめんとかにﾅﾍﾞとかに鍋";
+        let matches: Vec<_> = trie
+            .find_iter(haystack, MatchKind::LeftmostLongest)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![
+                (2, "Ｃｒａｂ", 0..4),
+                (0, "カﾆ", 5..7),
+                (3, "らｰめん", 10..14),
+                (0, "かに", 15..17),
+                (1, "かに鍋", 21..24),
+            ]
+        );
+        assert_eq!(trie.contains_search("ニ".chars()), Some(vec![0, 1]));
+        assert_eq!(trie.contains_search("めん".chars()), Some(vec![3]));
+        assert_eq!(
+            trie.predictive_search("か".chars()).collect::<Vec<_>>(),
+            vec![("カニ".to_string(), 0), ("カニ鍋".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        use crate::automaton::Levenshtein;
+
+        let keys = vec!["ｶﾆ", "ｸﾞﾗﾀﾝ", "ﾊﾟﾝ"];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .normalizer(crate::Normalizer::japanese())
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+
+        assert_eq!(trie.exact_match("グラタン".chars()), Some(1));
+        assert_eq!(trie.exact_match("ｸﾞﾗﾀﾝ".chars()), Some(1));
+        assert_eq!(trie.exact_match("ぐらたん".chars()), Some(1));
+        assert_eq!(trie.exact_match("く゛らたん".chars()), Some(1));
+        assert_eq!(trie.exact_match("ｸﾗﾀﾝ".chars()), None);
+        assert_eq!(
+            trie.common_prefix_search("ﾊﾟﾝ粉".chars())
+                .collect::<Vec<_>>(),
+            vec![(2, 3)]
+        );
+        assert_eq!(trie.longest_prefix_match("ｸﾞﾗﾀﾝ皿".chars()), Some((1, 5)));
+        assert_eq!(
+            trie.predictive_search("ｸﾞﾗ".chars()).collect::<Vec<_>>(),
+            vec![("グラタン".to_string(), 1)]
+        );
+
+        let haystack = "ﾊﾟﾝとｸﾞﾗﾀﾝとｶﾆ";
+        let matches: Vec<_> = trie
+            .find_overlapping_iter(haystack)
+            .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+            .collect();
+        assert_eq!(
+            matches,
+            vec![(2, "ﾊﾟﾝ", 0..3), (1, "ｸﾞﾗﾀﾝ", 4..9), (0, "ｶﾆ", 10..12)]
+        );
+        let matches: Vec<_> = trie
+            .find_iter(haystack, MatchKind::LeftmostLongest)
+            .map(|m| (m.value(), m.range_in_chars()))
+            .collect();
+        assert_eq!(matches, vec![(2, 0..3), (1, 4..9), (0, 10..12)]);
+
+        let automaton = Levenshtein::new(trie.normalize("ｸﾞﾗﾀﾝ".chars()), 0);
+        assert_eq!(
+            trie.search_with(automaton).collect::<Vec<_>>(),
+            vec![("グラタン".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_char_classes() {
+        let keys = vec!["高橋", "高", "橋本", "a-b"];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .char_classes([('髙', '高'), ('槗', '橋'), ('‐', '-'), ('−', '-')])
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+
+        assert_eq!(trie.exact_match("髙槗".chars()), Some(0));
+        assert_eq!(trie.exact_match("a−b".chars()), Some(3));
+        assert_eq!(trie.exact_match("a‐b".chars()), Some(3));
+        assert_eq!(
+            trie.common_prefix_search("髙槗本".chars())
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (0, 2)]
+        );
+        assert_eq!(
+            trie.common_prefix_search("槗本".chars())
+                .collect::<Vec<_>>(),
+            vec![(2, 2)]
+        );
+
+        // Chained classes are not closed transitively.
+        assert!(crate::Builder::new()
+            .char_classes([('a', 'b'), ('b', 'c')])
+            .build_from_keys(&keys)
+            .is_err());
+        // A character cannot belong to two classes.
+        assert!(crate::Builder::new()
+            .char_classes([('a', 'b'), ('a', 'c')])
+            .build_from_keys(&keys)
+            .is_err());
+        assert!(crate::Builder::new()
+            .char_classes([('a', 'b'), ('a', 'b')])
+            .build_from_keys(&keys)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_multi_values() {
+        let records = vec![
+            ("はし", 1),
+            ("はしら", 2),
+            ("はし", u32::MAX),
+            ("は", 4),
+            ("はし", 0),
+            ("はしら", 6),
+        ];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .multi_values()
+            .build_from_records(records)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+
+        assert_eq!(trie.exact_match("は".chars()), Some(0));
+        assert_eq!(trie.exact_match("はし".chars()), Some(1));
+        assert_eq!(trie.exact_match("はしら".chars()), Some(2));
+        assert_eq!(trie.values(0), Some(&[4][..]));
+        assert_eq!(trie.values(1), Some(&[1, u32::MAX, 0][..]));
+        assert_eq!(trie.values(2), Some(&[2, 6][..]));
+        assert_eq!(trie.values(3), None);
+        assert_eq!(trie.exact_match_values("はしご".chars()), None);
+
+        let trie = MpTrie::from_records(vec![("はし", 1), ("はしら", 2)]).unwrap();
+        assert_eq!(trie.values(0), None);
+        assert!(MpTrie::from_records(vec![("はし", 1), ("はし", 2)]).is_err());
     }
 
-    #[inline(always)]
-    fn get_child_idx(&self, node_idx: u32, mc: u32) -> Option<u32> {
-        if self.is_leaf(node_idx) {
-            return None;
+    #[test]
+    fn test_payloads() {
+        let records = vec![("はし", vec![1, 2, 3]), ("は", vec![]), ("はしら", vec![4])];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .build_from_payloads(records.clone())
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+
+        for (i, (key, payload)) in records.iter().enumerate() {
+            assert_eq!(
+                trie.exact_match(key.chars()),
+                Some(u32::try_from(i).unwrap())
+            );
+            assert_eq!(
+                trie.exact_match_payload(key.chars()),
+                Some(payload.as_slice())
+            );
         }
-        Some(self.get_base(node_idx) ^ mc)
-            .filter(|&child_idx| self.get_check(child_idx) == node_idx)
-    }
+        assert_eq!(trie.payload(3), None);
+        assert_eq!(trie.exact_match_payload("はしご".chars()), None);
 
-    #[inline(always)]
-    fn node_ref(&self, node_idx: u32) -> &Node {
-        &self.nodes[usize::try_from(node_idx).unwrap()]
+        // Payloads are indexed by the input order, not by the list ids of the multi-value mode.
+        let records = vec![("b", "B1"), ("a", "A1"), ("b", "B2")];
+        assert!(crate::Builder::new()
+            .multi_values()
+            .build_from_payloads(records)
+            .is_err());
     }
 
-    #[inline(always)]
-    fn get_base(&self, node_idx: u32) -> u32 {
-        self.node_ref(node_idx).get_base()
+    #[test]
+    fn test_single_key() {
+        let trie = MpTrie::from_keys(["世界中"]).unwrap();
+        assert_eq!(trie.exact_match("世界中".chars()), Some(0));
+        assert_eq!(trie.exact_match("世界".chars()), None);
+        assert_eq!(
+            trie.common_prefix_search("世界中で".chars())
+                .collect::<Vec<_>>(),
+            vec![(0, 3)]
+        );
     }
 
-    #[inline(always)]
-    fn get_check(&self, node_idx: u32) -> u32 {
-        self.node_ref(node_idx).get_check()
-    }
+    #[test]
+    fn test_wide_values() {
+        let records = vec![
+            ("世界", 1 << 31),
+            ("世界中", u64::MAX),
+            ("世", 0),
+            ("国民", 1 << 63),
+        ];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .build_from_wide_records(records.clone())
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
 
-    #[inline(always)]
-    fn is_leaf(&self, node_idx: u32) -> bool {
-        self.node_ref(node_idx).is_leaf()
-    }
+        for &(key, value) in &records {
+            assert_eq!(trie.exact_match_wide(key.chars()), Some(value));
+        }
+        assert_eq!(trie.exact_match_wide("国".chars()), None);
+        assert_eq!(trie.wide_value(4), None);
+        assert_eq!(
+            trie.common_prefix_search_wide("世界中で".chars())
+                .unwrap()
+                .collect::<Vec<_>>(),
+            vec![(0, 1), (1 << 31, 2), (u64::MAX, 3)]
+        );
 
-    #[inline(always)]
-    fn has_leaf(&self, node_idx: u32) -> bool {
-        self.node_ref(node_idx).has_leaf()
-    }
+        let trie = MpTrie::from_keys(["世界"]).unwrap();
+        assert_eq!(trie.wide_value(0), None);
+        assert!(trie.common_prefix_search_wide("世界".chars()).is_none());
 
-    #[inline(always)]
-    fn get_leaf_idx(&self, node_idx: u32) -> u32 {
-        let leaf_idx = self.get_base(node_idx) ^ END_CODE;
-        debug_assert_eq!(self.get_check(leaf_idx), node_idx);
-        leaf_idx
+        // Wide values are indexed by the input order, not by the list ids of the multi-value mode.
+        let records = vec![("b", 1 << 40), ("a", 1), ("b", 2)];
+        assert!(crate::Builder::new()
+            .multi_values()
+            .build_from_wide_records(records)
+            .is_err());
     }
 
-    #[inline(always)]
-    fn get_value(&self, node_idx: u32) -> u32 {
-        debug_assert!(self.is_leaf(node_idx));
-        self.node_ref(node_idx).get_base()
-    }
+    #[test]
+    fn test_ordered_search() {
+        // Frequent characters are assigned small codes, unlike the code-point order.
+        let keys = vec![
+            "ab", "abc", "abd", "ac", "b", "bbb", "bbbb", "bbc", "bbd", "d", "dddd",
+        ];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, &k)| (k.to_string(), u32::try_from(i).unwrap()))
+            .collect();
 
-    /// Returns the total amount of heap used by this automaton in bytes.
-    pub fn heap_bytes(&self) -> usize {
-        self.mapper.heap_bytes()
-            + self.nodes.len() * mem::size_of::<Node>()
-            + self.tails.len() * mem::size_of::<u8>()
+        let mut queries = vec!["".to_string(), "z".to_string(), "bbbbb".to_string()];
+        for &a in &['a', 'b', 'c', 'd', 'e'] {
+            queries.push(a.to_string());
+            for &b in &['a', 'b', 'c', 'd', 'e'] {
+                queries.push([a, b].iter().collect());
+                for &c in &['a', 'b', 'c', 'd', 'e'] {
+                    queries.push([a, b, c].iter().collect());
+                }
+            }
+        }
+        for q in &queries {
+            let q = q.as_str();
+            assert_eq!(
+                trie.lower_bound(q.chars()),
+                records.iter().find(|(k, _)| k.as_str() >= q).cloned()
+            );
+            assert_eq!(
+                trie.successor(q.chars()),
+                records.iter().find(|(k, _)| k.as_str() > q).cloned()
+            );
+            assert_eq!(
+                trie.predecessor(q.chars()),
+                records.iter().rev().find(|(k, _)| k.as_str() < q).cloned()
+            );
+            for hi in ["", "abd", "b", "bbbb", "bbc", "c", "z"] {
+                let results: Vec<_> = trie.range(q..hi).collect();
+                let expected: Vec<_> = records
+                    .iter()
+                    .filter(|(k, _)| q <= k.as_str() && k.as_str() < hi)
+                    .cloned()
+                    .collect();
+                assert_eq!(results, expected);
+                let results: Vec<_> = trie
+                    .range((Bound::Excluded(q), Bound::Included(hi)))
+                    .collect();
+                let expected: Vec<_> = records
+                    .iter()
+                    .filter(|(k, _)| q < k.as_str() && k.as_str() <= hi)
+                    .cloned()
+                    .collect();
+                assert_eq!(results, expected);
+            }
+        }
+        assert_eq!(
+            trie.range((Bound::<&str>::Unbounded, Bound::Unbounded))
+                .count(),
+            keys.len()
+        );
     }
 
-    /// Returns the total amount of bytes to serialize the data structure.
-    pub fn io_bytes(&self) -> usize {
-        self.mapper.io_bytes()
-            + self.nodes.len() * Node::io_bytes()
-            + mem::size_of::<u32>()
-            + self.tails.len() * mem::size_of::<u8>()
-            + mem::size_of::<u32>()
-            + mem::size_of::<u8>() * 2
-    }
+    #[test]
+    fn test_sibling_links() {
+        let keys = vec![
+            "ab", "abc", "abd", "ac", "b", "bbb", "bbc", "d", "世界", "世論",
+        ];
+        let build = |links: bool| {
+            let builder = crate::Builder::new().minimal_prefix();
+            let builder = if links {
+                builder.sibling_links()
+            } else {
+                builder
+            };
+            builder
+                .build_from_keys(&keys)
+                .unwrap()
+                .release_mptrie()
+                .unwrap()
+        };
+        let plain = build(false);
+        let linked = build(true);
+        assert!(!plain.siblings.has_links());
+        assert!(linked.siblings.has_links());
+        assert!(plain.heap_bytes() < linked.heap_bytes());
 
-    /// Returns the number of reserved elements.
-    pub fn num_elems(&self) -> usize {
-        self.nodes.len()
-    }
+        let bytes = linked.serialize_to_vec();
+        assert_eq!(linked.io_bytes(), bytes.len());
+        let (linked, _) = MpTrie::deserialize_from_slice(&bytes);
+        assert!(linked.siblings.has_links());
 
-    /// Returns the number of vacant elements.
-    ///
-    /// # Note
-    ///
-    /// It takes `O(num_elems)` time.
-    pub fn num_vacants(&self) -> usize {
-        self.nodes.iter().filter(|nd| nd.is_vacant()).count()
+        assert_eq!(
+            plain.iter().collect::<Vec<_>>(),
+            linked.iter().collect::<Vec<_>>()
+        );
+        for q in ["", "a", "ab", "b", "bb", "c", "世"] {
+            assert_eq!(
+                plain.predictive_search(q.chars()).collect::<Vec<_>>(),
+                linked.predictive_search(q.chars()).collect::<Vec<_>>()
+            );
+            assert_eq!(plain.successor(q.chars()), linked.successor(q.chars()));
+            assert_eq!(plain.predecessor(q.chars()), linked.predecessor(q.chars()));
+            assert_eq!(
+                plain.range(q..).collect::<Vec<_>>(),
+                linked.range(q..).collect::<Vec<_>>()
+            );
+        }
     }
-}
 
-/// Iterator for common prefix search.
-pub struct CommonPrefixSearchIter<'t, I> {
-    haystack: I,
-    haystack_pos: usize,
-    trie: &'t MpTrie,
-    node_idx: u32,
-}
-
-impl<I> Iterator for CommonPrefixSearchIter<'_, I>
-where
-    I: Iterator<Item = char>,
-{
-    type Item = (u32, usize);
+    #[test]
+    fn test_count_prefix() {
+        let keys = vec![
+            "ab", "abc", "abd", "ac", "b", "bbb", "bbbb", "bbc", "bbd", "d", "dddd",
+        ];
+        let trie = Builder::new()
+            .minimal_prefix()
+            .prefix_counts()
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
 
-    #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
-        for c in self.haystack.by_ref() {
-            let mc = self.trie.mapper.get(c)?;
-            self.node_idx = self.trie.get_child_idx(self.node_idx, mc)?;
-            self.haystack_pos += 1;
-            if self.trie.is_leaf(self.node_idx) {
-                let tail_pos = usize::try_from(self.trie.get_value(self.node_idx)).unwrap();
-                let mut tail_iter = self.trie.tail_iter(tail_pos);
-                for tc in tail_iter.by_ref() {
-                    let mc = self.trie.mapper.get(self.haystack.next()?);
-                    mc.filter(|&c| c == tc)?;
-                    self.haystack_pos += 1;
+        let mut prefixes = vec!["".to_string(), "ddddd".to_string(), "bbbbb".to_string()];
+        for &a in &['a', 'b', 'c', 'd', 'e'] {
+            prefixes.push(a.to_string());
+            for &b in &['a', 'b', 'c', 'd', 'e'] {
+                prefixes.push([a, b].iter().collect());
+                for &c in &['a', 'b', 'c', 'd', 'e'] {
+                    prefixes.push([a, b, c].iter().collect());
                 }
-                return Some((tail_iter.value(), self.haystack_pos));
-            } else if self.trie.has_leaf(self.node_idx) {
-                let leaf_idx = self.trie.get_leaf_idx(self.node_idx);
-                return Some((self.trie.get_value(leaf_idx), self.haystack_pos));
             }
         }
-        None
-    }
-}
+        for prefix in &prefixes {
+            let expected = keys
+                .iter()
+                .filter(|k| k.starts_with(prefix.as_str()))
+                .count();
+            assert_eq!(
+                trie.count_prefix(prefix.chars()),
+                Some(u32::try_from(expected).unwrap())
+            );
+        }
 
-struct TailIter<'a> {
-    trie: &'a MpTrie,
-    pos: usize,
-    len: usize,
-}
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+        assert_eq!(trie.count_prefix("bb".chars()), Some(4));
 
-impl TailIter<'_> {
-    #[inline(always)]
-    fn value(&self) -> u32 {
-        utils::unpack_u32(&self.trie.tails[self.pos..], self.trie.value_size)
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        assert_eq!(trie.count_prefix("a".chars()), None);
     }
-}
 
-impl Iterator for TailIter<'_> {
-    type Item = u32;
+    #[test]
+    fn test_top_k_completions() {
+        let records = vec![
+            ("ab", 3),
+            ("abc", 10),
+            ("abd", 1),
+            ("ac", 7),
+            ("b", 0),
+            ("bbb", 4),
+            ("bbbb", 9),
+            ("bbc", 2),
+            ("bbd", 8),
+            ("d", 5),
+            ("dddd", 6),
+        ];
+        let trie = Builder::new()
+            .minimal_prefix()
+            .build_from_weighted_keys(records.iter().copied())
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
 
-    #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.len != 0 {
-            let c = utils::unpack_u32(&self.trie.tails[self.pos..], self.trie.code_size);
-            self.pos += usize::try_from(self.trie.code_size).unwrap();
-            self.len -= 1;
-            Some(c)
-        } else {
-            None
+        let mut sorted: Vec<_> = records
+            .iter()
+            .enumerate()
+            .map(|(i, &(k, w))| (w, k.to_string(), u32::try_from(i).unwrap()))
+            .collect();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+
+        for prefix in [
+            "", "a", "ab", "abc", "abcd", "b", "bb", "bbbb", "c", "d", "ddd", "e",
+        ] {
+            for k in 0..=records.len() + 1 {
+                let expected: Vec<_> = sorted
+                    .iter()
+                    .filter(|(_, key, _)| key.starts_with(prefix))
+                    .take(k)
+                    .map(|(_, key, value)| (key.clone(), *value))
+                    .collect();
+                assert_eq!(trie.top_k_completions(prefix.chars(), k), Some(expected));
+            }
         }
+
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (trie, _) = MpTrie::deserialize_from_slice(&bytes);
+        assert_eq!(
+            trie.top_k_completions("bb".chars(), 1),
+            Some(vec![("bbbb".to_string(), 6)])
+        );
+
+        let trie = MpTrie::from_keys(records.iter().map(|&(k, _)| k)).unwrap();
+        assert_eq!(trie.top_k_completions("a".chars(), 1), None);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_top_k_completions_multi_values() {
+        let records = vec![("z", 100), ("a", 1), ("a", 2), ("b", 3)];
+        let trie = Builder::new()
+            .minimal_prefix()
+            .multi_values()
+            .build_from_weighted_keys(records)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+
+        // The values are the ids of the lists.
+        assert_eq!(
+            trie.top_k_completions("".chars(), 1),
+            Some(vec![("z".to_string(), 2)])
+        );
+        assert_eq!(
+            trie.top_k_completions("".chars(), 3),
+            Some(vec![
+                ("z".to_string(), 2),
+                ("b".to_string(), 1),
+                ("a".to_string(), 0)
+            ])
+        );
+        assert_eq!(trie.values(0), Some(&[1, 2][..]));
+    }
 
     #[test]
-    fn test_exact_match() {
+    fn test_cursor() {
         let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
         let trie = MpTrie::from_keys(&keys).unwrap();
-        for (i, key) in keys.iter().enumerate() {
-            assert_eq!(
-                trie.exact_match(key.chars()),
-                Some(u32::try_from(i).unwrap())
-            );
+
+        let mut cursor = trie.cursor();
+        let mut results = vec![];
+        for c in "世論調査".chars() {
+            cursor = cursor.step(c).unwrap();
+            results.push((cursor.value(), cursor.has_children(), cursor.depth()));
         }
-        assert_eq!(trie.exact_match("世".chars()), None);
-        assert_eq!(trie.exact_match("世論".chars()), None);
-        assert_eq!(trie.exact_match("世界中で".chars()), None);
-        assert_eq!(trie.exact_match("統計".chars()), None);
-        assert_eq!(trie.exact_match("統計調".chars()), None);
-        assert_eq!(trie.exact_match("日本".chars()), None);
+        assert_eq!(
+            results,
+            vec![
+                (None, true, 1),
+                (None, true, 2),
+                (None, true, 3),
+                (Some(2), false, 4)
+            ]
+        );
+        assert!(cursor.step('中').is_none());
+
+        let cursor = trie.cursor().step('世').unwrap();
+        assert!(cursor.step('中').is_none());
+        assert!(cursor.step('論').unwrap().step('査').is_none());
+        assert!(cursor.step(crate::END_MARKER).is_none());
+
+        let cursor = trie.cursor().step('世').unwrap().step('界').unwrap();
+        assert_eq!(cursor.value(), Some(0));
+        assert!(cursor.has_children());
+        let cursor = cursor.step('中').unwrap();
+        assert_eq!(cursor.value(), Some(1));
+        assert!(!cursor.has_children());
     }
 
     #[test]
-    fn test_common_prefix_search() {
-        let keys = vec!["世界", "世界中", "世論調査", "統計調査"];
+    fn test_predictive_search() {
+        let keys = vec!["世界", "世界中", "世界中央", "世論調査", "統計調査"];
         let trie = MpTrie::from_keys(&keys).unwrap();
 
-        let haystack: Vec<_> = "世界中の統計世論調査".chars().collect();
-        let mut matches = vec![];
+        let results: Vec<_> = trie.predictive_search("世界".chars()).collect();
+        assert_eq!(
+            results,
+            vec![
+                ("世界".to_string(), 0),
+                ("世界中".to_string(), 1),
+                ("世界中央".to_string(), 2),
+            ]
+        );
 
-        for i in 0..haystack.len() {
-            for (v, j) in trie.common_prefix_search(haystack[i..].iter().copied()) {
-                matches.push((v, i..i + j));
-            }
-        }
-        assert_eq!(matches, vec![(0, 0..2), (1, 0..3), (2, 6..10)]);
+        // Prefixes ending inside tails
+        let results: Vec<_> = trie.predictive_search("世論調".chars()).collect();
+        assert_eq!(results, vec![("世論調査".to_string(), 3)]);
+        let results: Vec<_> = trie.predictive_search("統計調査".chars()).collect();
+        assert_eq!(results, vec![("統計調査".to_string(), 4)]);
+
+        let results: Vec<_> = trie.predictive_search("".chars()).collect();
+        let expected: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, &k)| (k.to_string(), u32::try_from(i).unwrap()))
+            .collect();
+        assert_eq!(results, expected);
+
+        assert_eq!(trie.predictive_search("世界中で".chars()).next(), None);
+        assert_eq!(trie.predictive_search("世論調整".chars()).next(), None);
+        assert_eq!(trie.predictive_search("統計調査中".chars()).next(), None);
+        assert_eq!(trie.predictive_search("日本".chars()).next(), None);
+    }
+
+    #[test]
+    fn test_predictive_search_order() {
+        let keys = vec!["ab", "abc", "abd", "ac", "b", "bbb", "bbc", "bbd", "d"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        let results: Vec<_> = trie.predictive_search("".chars()).map(|(k, _)| k).collect();
+        assert_eq!(results, keys);
+    }
+
+    #[test]
+    fn test_iter() {
+        let records = vec![
+            ("世論調査", 4),
+            ("世界中", 3),
+            ("世界", 5),
+            ("統計調査", 1),
+            ("世", 2),
+        ];
+        let trie = MpTrie::from_records(records.clone()).unwrap();
+
+        let mut expected: Vec<_> = records.iter().map(|&(k, v)| (k.to_string(), v)).collect();
+        expected.sort_unstable();
+        assert_eq!(trie.iter().collect::<Vec<_>>(), expected);
+
+        let mut results = vec![];
+        trie.for_each_entry(|k, v| results.push((k.to_string(), v)));
+        assert_eq!(results, expected);
     }
 
     #[test]
@@ -477,9 +3450,93 @@ mod tests {
 
         assert_eq!(trie.mapper, other.mapper);
         assert_eq!(trie.nodes, other.nodes);
+        assert_eq!(trie.siblings, other.siblings);
         assert_eq!(trie.tails, other.tails);
         assert_eq!(trie.code_size, other.code_size);
         assert_eq!(trie.value_size, other.value_size);
+        assert_eq!(trie.sections.leaf_idxs, other.sections.leaf_idxs);
+        assert_eq!(trie.sections.ranks, other.sections.ranks);
+        assert_eq!(trie.sections.key_leaves, other.sections.key_leaves);
+    }
+
+    #[test]
+    fn test_serialize_compatibility() {
+        // Fingerprints of the data serialized with version 0.4.0.
+        let keys = vec!["世界", "世界中", "国民"];
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(bytes.len(), 262231);
+        let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| {
+            (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+        });
+        assert_eq!(hash, 0x80d7_754d_f0fa_359e);
+    }
+
+    #[test]
+    fn test_restore_key() {
+        let keys = vec!["世論調査", "世界中", "世界", "統計調査", "世"];
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .reverse_index()
+            .build_from_keys(&keys)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        for (i, &key) in keys.iter().enumerate() {
+            let value = u32::try_from(i).unwrap();
+            assert_eq!(trie.restore_key(value), Some(key.to_string()));
+        }
+        assert_eq!(trie.restore_key(5), None);
+
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (other, remain) = MpTrie::deserialize_from_slice(&bytes);
+        assert!(remain.is_empty());
+        assert_eq!(other.restore_key(3), Some("統計調査".to_string()));
+
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        assert_eq!(trie.restore_key(0), None);
+    }
+
+    #[test]
+    fn test_key_ids() {
+        let keys = vec!["世", "世界", "世界中", "世論調査", "統計", "統計調査"];
+        let records: Vec<_> = keys.iter().rev().map(|&k| (k, 42)).collect();
+        let trie = crate::Builder::new()
+            .minimal_prefix()
+            .key_ids()
+            .build_from_records(records)
+            .unwrap()
+            .release_mptrie()
+            .unwrap();
+        for (i, &key) in keys.iter().enumerate() {
+            let id = u32::try_from(i).unwrap();
+            assert_eq!(trie.key_id(key.chars()), Some(id));
+            assert_eq!(trie.key_at(id), Some(key.to_string()));
+        }
+        assert_eq!(trie.key_id("世論".chars()), None);
+        assert_eq!(trie.key_id("世論調".chars()), None);
+        assert_eq!(trie.key_id("統計調査中".chars()), None);
+        assert_eq!(trie.key_at(6), None);
+
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (other, remain) = MpTrie::deserialize_from_slice(&bytes);
+        assert!(remain.is_empty());
+        assert_eq!(other.key_id("統計".chars()), Some(4));
+        assert_eq!(other.key_at(4), Some("統計".to_string()));
+
+        let trie = MpTrie::from_keys(&keys).unwrap();
+        assert_eq!(trie.key_id("世".chars()), None);
+        assert_eq!(trie.key_at(0), None);
+    }
+
+    #[test]
+    fn test_reverse_index_invalid_values() {
+        let builder = crate::Builder::new().minimal_prefix().reverse_index();
+        assert!(builder.build_from_records([("AA", 0), ("BB", 2)]).is_err());
+        let builder = crate::Builder::new().minimal_prefix().reverse_index();
+        assert!(builder.build_from_records([("AA", 1), ("BB", 1)]).is_err());
     }
 
     #[test]
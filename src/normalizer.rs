@@ -0,0 +1,355 @@
+//! Character-wise text normalization applied at build and query time.
+//!
+//! A [`Normalizer`] given to [`Builder::normalizer()`](crate::Builder::normalizer)
+//! normalizes keys when building a dictionary, and the dictionary maps every character
+//! to the code of its normalized form at query time.
+//! With the width folding, a kana followed by a voiced sound mark such as `ｸﾞ` is also
+//! composed into the voiced kana `グ` at build and query time.
+//! Query results report positions in the original, unnormalized haystacks.
+//!
+//! # Examples
+//!
+//! ```
+//! use crawdad::{Builder, Normalizer};
+//!
+//! let keys = vec!["ｶﾆ", "ぐらたん", "ＣＲＡＢ"];
+//! let trie = Builder::new()
+//!     .normalizer(Normalizer::japanese())
+//!     .build_from_keys(&keys)
+//!     .unwrap()
+//!     .release_trie()
+//!     .unwrap();
+//!
+//! assert_eq!(trie.exact_match("カニ".chars()), Some(0));
+//! assert_eq!(trie.exact_match("グラタン".chars()), Some(1));
+//! assert_eq!(trie.exact_match("ｸﾞﾗﾀﾝ".chars()), Some(1));
+//! assert_eq!(trie.exact_match("crab".chars()), Some(2));
+//!
+//! let haystack = "焼きｶﾆとｸﾞﾗﾀﾝとCrab";
+//! let matches: Vec<_> = trie
+//!     .find_overlapping_iter(haystack)
+//!     .map(|m| (m.value(), &haystack[m.range()], m.range_in_chars()))
+//!     .collect();
+//! assert_eq!(
+//!     matches,
+//!     vec![(0, "ｶﾆ", 2..4), (1, "ｸﾞﾗﾀﾝ", 5..10), (2, "Crab", 11..15)]
+//! );
+//! ```
+//!
+//! Note that [`Trie::cursor()`](crate::Trie::cursor) and the automata given to
+//! [`Trie::search_with()`](crate::Trie::search_with) take characters one by one, so the sound
+//! marks are not composed there. Use [`Trie::normalize()`](crate::Trie::normalize) to give them
+//! normalized characters.
+use core::ops::RangeInclusive;
+
+// Full-width forms of the half-width katakana in U+FF61..=U+FF9F.
+const HALFWIDTH_KATAKANA: [char; 63] = [
+    '。', '「', '」', '、', '・', 'ヲ', 'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ッ', 'ー',
+    'ア', 'イ', 'ウ', 'エ', 'オ', 'カ', 'キ', 'ク', 'ケ', 'コ', 'サ', 'シ', 'ス', 'セ', 'ソ', 'タ',
+    'チ', 'ツ', 'テ', 'ト', 'ナ', 'ニ', 'ヌ', 'ネ', 'ノ', 'ハ', 'ヒ', 'フ', 'ヘ', 'ホ', 'マ', 'ミ',
+    'ム', 'メ', 'モ', 'ヤ', 'ユ', 'ヨ', 'ラ', 'リ', 'ル', 'レ', 'ロ', 'ワ', 'ン', '゛', '゜',
+];
+
+// Variants of the prolonged sound mark `ー`.
+const LONG_VOWEL_VARIANTS: [char; 7] = ['—', '―', '─', '━', '﹣', '－', 'ｰ'];
+
+// The range of characters affected by the normalization, which covers all the characters
+// folded by the width, kana, and long vowel foldings. The case folding is also restricted to
+// this range to keep the number of aliases small.
+const DOMAIN: RangeInclusive<u32> = 0..=0xffff;
+
+/// Character-wise normalizer of keys and haystacks.
+///
+/// All the foldings are disabled by default.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Normalizer {
+    width: bool,
+    kana: bool,
+    case: bool,
+    long_vowel: bool,
+}
+
+impl Normalizer {
+    /// Creates a new [`Normalizer`] with all the foldings disabled.
+    pub const fn new() -> Self {
+        Self {
+            width: false,
+            kana: false,
+            case: false,
+            long_vowel: false,
+        }
+    }
+
+    /// Creates a new [`Normalizer`] with all the foldings enabled.
+    pub const fn japanese() -> Self {
+        Self::new()
+            .fold_width()
+            .fold_kana()
+            .fold_case()
+            .fold_long_vowels()
+    }
+
+    /// Enables the folding of full-width ASCII characters and the ideographic space
+    /// into half-width ones, and half-width katakana into full-width ones.
+    ///
+    /// A kana followed by a voiced or semi-voiced sound mark, such as `ｸﾞ` or `ク゛`,
+    /// is also composed into a voiced kana, such as `グ`.
+    pub const fn fold_width(mut self) -> Self {
+        self.width = true;
+        self
+    }
+
+    /// Enables the folding of hiragana into katakana.
+    pub const fn fold_kana(mut self) -> Self {
+        self.kana = true;
+        self
+    }
+
+    /// Enables the folding of upper-case characters into lower-case ones
+    /// in the Basic Multilingual Plane.
+    pub const fn fold_case(mut self) -> Self {
+        self.case = true;
+        self
+    }
+
+    /// Enables the folding of dash-like variants of the prolonged sound mark into `ー`.
+    pub const fn fold_long_vowels(mut self) -> Self {
+        self.long_vowel = true;
+        self
+    }
+
+    /// Returns the normalized form of a character.
+    ///
+    /// Characters outside the Basic Multilingual Plane are returned as they are.
+    /// The composition of sound marks is not performed since it is not character-wise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::Normalizer;
+    ///
+    /// let normalizer = Normalizer::japanese();
+    /// assert_eq!(normalizer.normalize('Ａ'), 'a');
+    /// assert_eq!(normalizer.normalize('ｶ'), 'カ');
+    /// assert_eq!(normalizer.normalize('か'), 'カ');
+    /// assert_eq!(normalizer.normalize('―'), 'ー');
+    /// ```
+    pub fn normalize(&self, mut c: char) -> char {
+        if !DOMAIN.contains(&u32::from(c)) {
+            return c;
+        }
+        if self.long_vowel && LONG_VOWEL_VARIANTS.contains(&c) {
+            c = 'ー';
+        }
+        if self.width {
+            c = match u32::from(c) {
+                0x3000 => ' ',
+                x @ 0xff01..=0xff5e => char::from_u32(x - 0xfee0).unwrap(),
+                x @ 0xff61..=0xff9f => HALFWIDTH_KATAKANA[usize::try_from(x - 0xff61).unwrap()],
+                _ => c,
+            };
+        }
+        if self.case {
+            let mut lower = c.to_lowercase();
+            if let (Some(l), None) = (lower.next(), lower.next()) {
+                c = l;
+            }
+        }
+        if self.kana {
+            c = match u32::from(c) {
+                x @ (0x3041..=0x3096 | 0x309d..=0x309e) => char::from_u32(x + 0x60).unwrap(),
+                _ => c,
+            };
+        }
+        c
+    }
+
+    /// Checks if kana and the following sound marks are composed.
+    pub(crate) const fn composes(&self) -> bool {
+        self.width
+    }
+
+    /// Returns pairs of characters and their normalized forms that differ.
+    pub(crate) fn aliases(&self) -> impl Iterator<Item = (char, char)> + '_ {
+        DOMAIN.filter_map(char::from_u32).filter_map(move |c| {
+            let n = self.normalize(c);
+            (n != c).then(|| (c, n))
+        })
+    }
+}
+
+/// Returns the voiced or semi-voiced kana composed of a kana and a following sound mark
+/// if exists, where the half-width forms and the combining marks are also accepted.
+///
+/// The result is katakana for (half-width) katakana and hiragana for hiragana.
+pub(crate) fn compose(c: char, mark: char) -> Option<char> {
+    let mark = match mark {
+        '\u{ff9e}' | '\u{309b}' | '\u{3099}' => 1,
+        '\u{ff9f}' | '\u{309c}' | '\u{309a}' => 2,
+        _ => return None,
+    };
+    let x = match u32::from(c) {
+        x @ 0xff61..=0xff9f => u32::from(HALFWIDTH_KATAKANA[usize::try_from(x - 0xff61).unwrap()]),
+        x => x,
+    };
+    // Hiragana are composed as the corresponding katakana.
+    let (x, offset) = match x {
+        0x3041..=0x309e => (x + 0x60, 0x60),
+        _ => (x, 0),
+    };
+    let composed = match (x, mark) {
+        // カ, キ, ..., チ
+        (0x30ab..=0x30c1, 1) if (x - 0x30ab) % 2 == 0 => x + 1,
+        // ツ, テ, ト
+        (0x30c4..=0x30c8, 1) if (x - 0x30c4) % 2 == 0 => x + 1,
+        // ハ, ヒ, フ, ヘ, ホ
+        (0x30cf..=0x30db, _) if (x - 0x30cf) % 3 == 0 => x + mark,
+        // ウ
+        (0x30a6, 1) => 0x30f4,
+        // ワ, ヰ, ヱ, ヲ, which have no voiced forms in hiragana.
+        (0x30ef..=0x30f2, 1) if offset == 0 => x + 8,
+        // ヽ
+        (0x30fd, 1) => 0x30fe,
+        _ => return None,
+    };
+    char::from_u32(composed - offset)
+}
+
+/// Iterator over characters in which kana followed by sound marks are composed if enabled,
+/// yielding each character with the number of the original characters.
+#[derive(Clone)]
+pub(crate) struct Composer<I> {
+    chars: I,
+    peeked: Option<char>,
+    enabled: bool,
+}
+
+impl<I> Composer<I> {
+    pub(crate) const fn new(chars: I, enabled: bool) -> Self {
+        Self {
+            chars,
+            peeked: None,
+            enabled,
+        }
+    }
+}
+
+impl<I> Iterator for Composer<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = (char, usize);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let c = match self.peeked.take() {
+            Some(c) => c,
+            None => self.chars.next()?,
+        };
+        if self.enabled {
+            if let Some(mark) = self.chars.next() {
+                if let Some(composed) = compose(c, mark) {
+                    return Some((composed, 2));
+                }
+                self.peeked = Some(mark);
+            }
+        }
+        Some((c, 1))
+    }
+}
+
+/// Returns the first character of `text` composed with a following sound mark if `enabled`,
+/// with the numbers of the original bytes and characters.
+#[inline(always)]
+pub(crate) fn compose_first(text: &str, enabled: bool) -> Option<(char, usize, usize)> {
+    let mut chars = text.chars();
+    let c = chars.next()?;
+    if enabled {
+        if let Some(mark) = chars.next() {
+            if let Some(composed) = compose(c, mark) {
+                return Some((composed, c.len_utf8() + mark.len_utf8(), 2));
+            }
+        }
+    }
+    Some((c, c.len_utf8(), 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = Normalizer::japanese();
+        let normalize = |s: &str| -> alloc::string::String {
+            s.chars().map(|c| normalizer.normalize(c)).collect()
+        };
+        assert_eq!(normalize("ＣＲＡＢ　ｸﾗﾌ"), "crab クラフ");
+        assert_eq!(normalize("かにカニｶﾆ"), "カニカニカニ");
+        assert_eq!(
+            normalize("ラーメン ラ―メン ラｰメン ラ－メン"),
+            "ラーメン ラーメン ラーメン ラーメン"
+        );
+        assert_eq!(normalize("ゝゞ"), "ヽヾ");
+
+        let normalizer = Normalizer::new().fold_case();
+        assert_eq!(normalizer.normalize('Ａ'), 'ａ');
+        assert_eq!(normalizer.normalize('か'), 'か');
+        // Characters outside the domain are not folded.
+        assert_eq!(normalizer.normalize('\u{10400}'), '\u{10400}');
+    }
+
+    #[test]
+    fn test_compose() {
+        let compose = |s: &str, enabled: bool| -> alloc::vec::Vec<(char, usize)> {
+            Composer::new(s.chars(), enabled).collect()
+        };
+        assert_eq!(compose("ｸﾞﾗﾌﾟ", true), vec![('グ', 2), ('ﾗ', 1), ('プ', 2)]);
+        assert_eq!(compose("ｸﾞﾗﾌﾟ", false).len(), 5);
+        assert_eq!(
+            compose("が゛は゜ヴ", true),
+            vec![('が', 1), ('゛', 1), ('ぱ', 2), ('ヴ', 1)]
+        );
+        assert_eq!(
+            compose("か\u{3099}ウ゛ゝ゛", true),
+            vec![('が', 2), ('ヴ', 2), ('ゞ', 2)]
+        );
+        assert_eq!(
+            compose("ﾜﾞわ゛ﾝﾞ", true),
+            vec![('ヷ', 2), ('わ', 1), ('゛', 1), ('ﾝ', 1), ('ﾞ', 1)]
+        );
+        assert_eq!(compose("ﾞｸ", true), vec![('ﾞ', 1), ('ｸ', 1)]);
+
+        assert_eq!(compose_first("ﾍﾟﾝ", true), Some(('ペ', 6, 2)));
+        assert_eq!(compose_first("ﾍﾟﾝ", false), Some(('ﾍ', 3, 1)));
+        assert_eq!(compose_first("", true), None);
+
+        // All the voiced kana are composed of their bases and marks.
+        let composed = |range: RangeInclusive<u32>, mark: char| -> alloc::string::String {
+            range
+                .filter_map(char::from_u32)
+                .filter_map(|c| super::compose(c, mark))
+                .collect()
+        };
+        assert_eq!(
+            composed(0x30a0..=0x30ff, '゛'),
+            "ヴガギグゲゴザジズゼゾダヂヅデドバビブベボヷヸヹヺヾ"
+        );
+        assert_eq!(composed(0x30a0..=0x30ff, '゜'), "パピプペポ");
+        assert_eq!(
+            composed(0x3040..=0x309f, '゛'),
+            "ゔがぎぐげござじずぜぞだぢづでどばびぶべぼゞ"
+        );
+        assert_eq!(composed(0x3040..=0x309f, '゜'), "ぱぴぷぺぽ");
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let normalizer = Normalizer::japanese();
+        for (c, n) in normalizer.aliases() {
+            assert_ne!(c, n);
+            assert_eq!(normalizer.normalize(n), n);
+        }
+        assert_eq!(Normalizer::new().aliases().count(), 0);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::errors::{CrawdadError, Result};
+use crate::infix::InfixIndex;
+use crate::mapper::CodeMapper;
+use crate::sparse::SparseValues;
+use crate::utils;
+
+use alloc::vec::Vec;
+
+use core::mem::size_of;
+
+// Magic bytes heading the serialized data with sections. The plain layout of version 0.4.0
+// starts with the length of the code table, which never exceeds 0x110000,
+// whereas the magic bytes are read as a larger integer.
+const MAGIC: [u8; 4] = *b"CRWD";
+
+// Version of the layout following the magic bytes.
+const VERSION: u32 = 1;
+
+// Bits of the present sections in the header, in the order of serialization.
+const LEAF_IDXS: u32 = 1 << 0;
+const RANKS: u32 = 1 << 1;
+const KEY_LEAVES: u32 = 1 << 2;
+const INFIX: u32 = 1 << 3;
+const VALUE_OFFSETS: u32 = 1 << 4;
+const VALUE_LISTS: u32 = 1 << 5;
+const PAYLOAD_OFFSETS: u32 = 1 << 6;
+const PAYLOADS: u32 = 1 << 7;
+const WIDE_VALUES: u32 = 1 << 8;
+const COUNTS: u32 = 1 << 9;
+const MAX_WEIGHTS: u32 = 1 << 10;
+// Characters mapped from codes, which cannot be derived from the code table with aliases.
+const CHARS: u32 = 1 << 11;
+// Composition of kana and the following sound marks, which has no data.
+const COMPOSE: u32 = 1 << 12;
+// Sibling links, which are derived from the double array and have no data.
+const SIBLING_LINKS: u32 = 1 << 13;
+
+/// Optional sections of [`Trie`](crate::Trie) and [`MpTrie`](crate::MpTrie),
+/// each of which is enabled by an option of [`Builder`](crate::Builder).
+///
+/// If any section is present, the serialized data starts with a header of the magic bytes,
+/// the version, and the bitmask of the present sections, and the sections follow the plain
+/// layout without any flag. Otherwise, the header is omitted to keep the layout of
+/// version 0.4.0.
+#[derive(Default)]
+pub struct Sections {
+    pub leaf_idxs: Option<Vec<u32>>,
+    pub ranks: Option<SparseValues>,
+    pub key_leaves: Option<Vec<u32>>,
+    pub infix: Option<InfixIndex>,
+    pub value_offsets: Option<Vec<u32>>,
+    pub value_lists: Option<Vec<u32>>,
+    pub payload_offsets: Option<Vec<u32>>,
+    pub payloads: Option<Vec<u8>>,
+    pub wide_values: Option<Vec<u64>>,
+    pub counts: Option<SparseValues>,
+    pub max_weights: Option<Vec<u32>>,
+    pub sibling_links: bool,
+}
+
+impl Sections {
+    /// Checks that no section is enabled, as required by [`AcTrie`](crate::AcTrie).
+    pub const fn check_disabled(&self) -> Result<()> {
+        if self.leaf_idxs.is_some() {
+            return Err(CrawdadError::setup("reverse_index must be disabled."));
+        }
+        if self.ranks.is_some() {
+            return Err(CrawdadError::setup("key_ids must be disabled."));
+        }
+        if self.infix.is_some() {
+            return Err(CrawdadError::setup("infix_index must be disabled."));
+        }
+        if self.value_offsets.is_some() {
+            return Err(CrawdadError::setup("multi_values must be disabled."));
+        }
+        if self.counts.is_some() {
+            return Err(CrawdadError::setup("prefix_counts must be disabled."));
+        }
+        if self.payloads.is_some() {
+            return Err(CrawdadError::setup("payloads are not supported."));
+        }
+        if self.wide_values.is_some() {
+            return Err(CrawdadError::setup("wide values are not supported."));
+        }
+        if self.max_weights.is_some() {
+            return Err(CrawdadError::setup("weights are not supported."));
+        }
+        if self.sibling_links {
+            return Err(CrawdadError::setup("sibling_links must be disabled."));
+        }
+        Ok(())
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        utils::vec_option_heap_bytes(self.leaf_idxs.as_ref())
+            + SparseValues::option_heap_bytes(self.ranks.as_ref())
+            + utils::vec_option_heap_bytes(self.key_leaves.as_ref())
+            + InfixIndex::option_heap_bytes(self.infix.as_ref())
+            + utils::vec_option_heap_bytes(self.value_offsets.as_ref())
+            + utils::vec_option_heap_bytes(self.value_lists.as_ref())
+            + utils::vec_option_heap_bytes(self.payload_offsets.as_ref())
+            + utils::vec_option_heap_bytes(self.payloads.as_ref())
+            + utils::vec_option_heap_bytes(self.wide_values.as_ref())
+            + SparseValues::option_heap_bytes(self.counts.as_ref())
+            + utils::vec_option_heap_bytes(self.max_weights.as_ref())
+    }
+
+    /// Returns the bitmask of the present sections.
+    fn mask(&self, mapper: &CodeMapper) -> u32 {
+        let mut mask = 0;
+        for (bit, present) in [
+            (LEAF_IDXS, self.leaf_idxs.is_some()),
+            (RANKS, self.ranks.is_some()),
+            (KEY_LEAVES, self.key_leaves.is_some()),
+            (INFIX, self.infix.is_some()),
+            (VALUE_OFFSETS, self.value_offsets.is_some()),
+            (VALUE_LISTS, self.value_lists.is_some()),
+            (PAYLOAD_OFFSETS, self.payload_offsets.is_some()),
+            (PAYLOADS, self.payloads.is_some()),
+            (WIDE_VALUES, self.wide_values.is_some()),
+            (COUNTS, self.counts.is_some()),
+            (MAX_WEIGHTS, self.max_weights.is_some()),
+            (CHARS, mapper.has_aliases()),
+            (COMPOSE, mapper.composes()),
+            (SIBLING_LINKS, self.sibling_links),
+        ] {
+            if present {
+                mask |= bit;
+            }
+        }
+        mask
+    }
+
+    /// Returns the number of bytes to serialize the header and the sections.
+    pub fn io_bytes(&self, mapper: &CodeMapper) -> usize {
+        if self.mask(mapper) == 0 {
+            return 0;
+        }
+        MAGIC.len()
+            + size_of::<u32>() * 2
+            + utils::vec_option_io_bytes(self.leaf_idxs.as_ref())
+            + SparseValues::option_io_bytes(self.ranks.as_ref())
+            + utils::vec_option_io_bytes(self.key_leaves.as_ref())
+            + InfixIndex::option_io_bytes(self.infix.as_ref())
+            + utils::vec_option_io_bytes(self.value_offsets.as_ref())
+            + utils::vec_option_io_bytes(self.value_lists.as_ref())
+            + utils::vec_option_io_bytes(self.payload_offsets.as_ref())
+            + utils::vec_option_io_bytes(self.payloads.as_ref())
+            + utils::vec_option_io_bytes(self.wide_values.as_ref())
+            + SparseValues::option_io_bytes(self.counts.as_ref())
+            + utils::vec_option_io_bytes(self.max_weights.as_ref())
+            + if mapper.has_aliases() {
+                mapper.chars_io_bytes()
+            } else {
+                0
+            }
+    }
+
+    /// Serializes the header into `dest` if any section is present.
+    pub fn serialize_header_into_vec(&self, mapper: &CodeMapper, dest: &mut Vec<u8>) {
+        let mask = self.mask(mapper);
+        if mask != 0 {
+            dest.extend_from_slice(&MAGIC);
+            dest.extend_from_slice(&VERSION.to_le_bytes());
+            dest.extend_from_slice(&mask.to_le_bytes());
+        }
+    }
+
+    /// Deserializes the header from `source` and returns the bitmask of the present sections,
+    /// which is zero for the plain layout.
+    ///
+    /// # Panics
+    ///
+    /// It panics if the version is not supported.
+    pub fn deserialize_header_from_slice(source: &[u8]) -> (u32, &[u8]) {
+        if source[..4] != MAGIC {
+            return (0, source);
+        }
+        let version = u32::from_le_bytes(source[4..8].try_into().unwrap());
+        assert_eq!(version, VERSION, "unsupported serialization version");
+        let mask = u32::from_le_bytes(source[8..12].try_into().unwrap());
+        (mask, &source[12..])
+    }
+
+    /// Serializes the present sections into `dest`.
+    pub fn serialize_into_vec(&self, mapper: &CodeMapper, dest: &mut Vec<u8>) {
+        utils::serialize_vec_option(dest, self.leaf_idxs.as_ref());
+        SparseValues::serialize_option(dest, self.ranks.as_ref());
+        utils::serialize_vec_option(dest, self.key_leaves.as_ref());
+        InfixIndex::serialize_option(dest, self.infix.as_ref());
+        utils::serialize_vec_option(dest, self.value_offsets.as_ref());
+        utils::serialize_vec_option(dest, self.value_lists.as_ref());
+        utils::serialize_vec_option(dest, self.payload_offsets.as_ref());
+        utils::serialize_vec_option(dest, self.payloads.as_ref());
+        utils::serialize_vec_option(dest, self.wide_values.as_ref());
+        SparseValues::serialize_option(dest, self.counts.as_ref());
+        utils::serialize_vec_option(dest, self.max_weights.as_ref());
+        if mapper.has_aliases() {
+            mapper.serialize_chars_into_vec(dest);
+        }
+    }
+
+    /// Deserializes the sections indicated by `mask` from `source`,
+    /// restoring the characters of `mapper` if needed.
+    pub fn deserialize_from_slice<'a>(
+        mask: u32,
+        mapper: &mut CodeMapper,
+        source: &'a [u8],
+    ) -> (Self, &'a [u8]) {
+        let (leaf_idxs, source) = utils::deserialize_vec_option(mask & LEAF_IDXS != 0, source);
+        let (ranks, source) = SparseValues::deserialize_option(mask & RANKS != 0, source);
+        let (key_leaves, source) = utils::deserialize_vec_option(mask & KEY_LEAVES != 0, source);
+        let (infix, source) = InfixIndex::deserialize_option(mask & INFIX != 0, source);
+        let (value_offsets, source) =
+            utils::deserialize_vec_option(mask & VALUE_OFFSETS != 0, source);
+        let (value_lists, source) = utils::deserialize_vec_option(mask & VALUE_LISTS != 0, source);
+        let (payload_offsets, source) =
+            utils::deserialize_vec_option(mask & PAYLOAD_OFFSETS != 0, source);
+        let (payloads, source) = utils::deserialize_vec_option(mask & PAYLOADS != 0, source);
+        let (wide_values, source) = utils::deserialize_vec_option(mask & WIDE_VALUES != 0, source);
+        let (counts, source) = SparseValues::deserialize_option(mask & COUNTS != 0, source);
+        let (max_weights, source) = utils::deserialize_vec_option(mask & MAX_WEIGHTS != 0, source);
+        let source = if mask & CHARS != 0 {
+            mapper.deserialize_chars_from_slice(source)
+        } else {
+            source
+        };
+        if mask & COMPOSE != 0 {
+            mapper.enable_composition();
+        }
+        (
+            Self {
+                leaf_idxs,
+                ranks,
+                key_leaves,
+                infix,
+                value_offsets,
+                value_lists,
+                payload_offsets,
+                payloads,
+                wide_values,
+                counts,
+                max_weights,
+                sibling_links: mask & SIBLING_LINKS != 0,
+            },
+            source,
+        )
+    }
+}
@@ -0,0 +1,139 @@
+use crate::mapper::CodeMapper;
+use crate::Node;
+
+use crate::{END_CODE, INVALID_IDX};
+
+use alloc::vec::Vec;
+
+use core::mem::size_of;
+use core::slice;
+
+/// Enumerator of the children of a node in the order of their characters.
+///
+/// By default, children are found by probing the codes in the order of their characters,
+/// which takes time proportional to the alphabet size for each node. If the sibling links
+/// are enabled with [`Builder::sibling_links()`](crate::Builder::sibling_links), children
+/// are enumerated in time proportional to their number, at the cost of two integers per node.
+///
+/// Both are derived from the double array and are not serialized.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Siblings {
+    // Codes except END_CODE sorted in the order of their characters.
+    ordered_codes: Vec<u32>,
+    // The following members are empty if the sibling links are disabled.
+    firsts: Vec<u32>,
+    nexts: Vec<u32>,
+}
+
+impl Siblings {
+    pub fn new(nodes: &[Node], mapper: &CodeMapper, links: bool) -> Self {
+        let mut ordered_codes: Vec<u32> = (0..mapper.alphabet_size())
+            .filter(|&code| code != END_CODE)
+            .collect();
+        ordered_codes.sort_unstable_by_key(|&code| mapper.get_char(code));
+        if !links {
+            return Self {
+                ordered_codes,
+                ..Self::default()
+            };
+        }
+
+        let mut edges = Vec::with_capacity(nodes.len());
+        for (node_idx, node) in nodes.iter().enumerate().skip(1) {
+            if node.is_vacant() {
+                continue;
+            }
+            let node_idx = u32::try_from(node_idx).unwrap();
+            let parent_idx = node.get_check();
+            let code = nodes[usize::try_from(parent_idx).unwrap()].get_base() ^ node_idx;
+            if code == END_CODE {
+                continue;
+            }
+            edges.push((parent_idx, mapper.get_char(code), node_idx));
+        }
+        edges.sort_unstable();
+
+        let mut firsts = vec![INVALID_IDX; nodes.len()];
+        let mut nexts = vec![INVALID_IDX; nodes.len()];
+        for &(parent_idx, _, node_idx) in edges.iter().rev() {
+            let parent_idx = usize::try_from(parent_idx).unwrap();
+            nexts[usize::try_from(node_idx).unwrap()] = firsts[parent_idx];
+            firsts[parent_idx] = node_idx;
+        }
+        Self {
+            ordered_codes,
+            firsts,
+            nexts,
+        }
+    }
+
+    /// Checks if the sibling links are enabled.
+    #[inline(always)]
+    pub fn has_links(&self) -> bool {
+        !self.firsts.is_empty()
+    }
+
+    /// Returns an iterator over the children of `node_idx` in the order of their characters,
+    /// excluding the one indicated with END_CODE.
+    #[inline(always)]
+    pub fn children<'a>(&'a self, nodes: &'a [Node], node_idx: u32) -> SiblingIter<'a> {
+        if self.has_links() {
+            SiblingIter::Links {
+                siblings: self,
+                node_idx: self.firsts[usize::try_from(node_idx).unwrap()],
+            }
+        } else {
+            SiblingIter::Probe {
+                nodes,
+                codes: self.ordered_codes.iter(),
+                base: nodes[usize::try_from(node_idx).unwrap()].get_base(),
+                parent_idx: node_idx,
+            }
+        }
+    }
+
+    #[inline]
+    pub fn heap_bytes(&self) -> usize {
+        (self.ordered_codes.len() + self.firsts.len() + self.nexts.len()) * size_of::<u32>()
+    }
+}
+
+/// Iterator created by [`Siblings::children()`].
+pub enum SiblingIter<'a> {
+    Links {
+        siblings: &'a Siblings,
+        node_idx: u32,
+    },
+    Probe {
+        nodes: &'a [Node],
+        codes: slice::Iter<'a, u32>,
+        base: u32,
+        parent_idx: u32,
+    },
+}
+
+impl Iterator for SiblingIter<'_> {
+    type Item = u32;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Links { siblings, node_idx } => {
+                if *node_idx == INVALID_IDX {
+                    return None;
+                }
+                let child_idx = *node_idx;
+                *node_idx = siblings.nexts[usize::try_from(child_idx).unwrap()];
+                Some(child_idx)
+            }
+            Self::Probe {
+                nodes,
+                codes,
+                base,
+                parent_idx,
+            } => codes.map(|&mc| *base ^ mc).find(|&child_idx| {
+                nodes[usize::try_from(child_idx).unwrap()].get_check() == *parent_idx
+            }),
+        }
+    }
+}
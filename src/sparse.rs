@@ -0,0 +1,147 @@
+use crate::utils;
+
+use alloc::vec::Vec;
+
+use core::mem::size_of;
+
+/// Values attached to a subset of nodes.
+///
+/// The nodes having values are marked in a bit vector, and the value of a marked node is
+/// located by the number of marked nodes before it. A rank of the bit vector is sampled for
+/// each word, so the nodes without values take only 1.5 bits each instead of a full value.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SparseValues {
+    words: Vec<u64>,
+    ranks: Vec<u32>,
+    values: Vec<u32>,
+}
+
+impl SparseValues {
+    /// Creates the values for `len` nodes from pairs of node indices in ascending order
+    /// and their values.
+    pub fn new<I>(len: usize, entries: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, u32)>,
+    {
+        let mut words = vec![0; (len + 63) / 64];
+        let mut values = vec![];
+        for (idx, value) in entries {
+            debug_assert!(idx < len);
+            words[idx / 64] |= 1 << (idx % 64);
+            values.push(value);
+        }
+        Self {
+            ranks: sample_ranks(&words),
+            words,
+            values,
+        }
+    }
+
+    /// Returns the value of `node_idx` if exists.
+    #[inline(always)]
+    pub fn get(&self, node_idx: u32) -> Option<u32> {
+        let idx = usize::try_from(node_idx).unwrap();
+        let word = *self.words.get(idx / 64)?;
+        let bit = 1 << (idx % 64);
+        if word & bit == 0 {
+            return None;
+        }
+        let rank = self.ranks[idx / 64] + (word & (bit - 1)).count_ones();
+        Some(self.values[usize::try_from(rank).unwrap()])
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        self.words.len() * size_of::<u64>()
+            + (self.ranks.len() + self.values.len()) * size_of::<u32>()
+    }
+
+    pub fn io_bytes(&self) -> usize {
+        // The ranks are recomputed in deserialization.
+        utils::vec_option_io_bytes(Some(&self.words))
+            + utils::vec_option_io_bytes(Some(&self.values))
+    }
+
+    pub fn serialize_into_vec(&self, dest: &mut Vec<u8>) {
+        utils::serialize_vec_option(dest, Some(&self.words));
+        utils::serialize_vec_option(dest, Some(&self.values));
+    }
+
+    pub fn deserialize_from_slice(source: &[u8]) -> (Self, &[u8]) {
+        let (words, source) = utils::deserialize_vec_option::<u64>(true, source);
+        let (values, source) = utils::deserialize_vec_option(true, source);
+        let words = words.unwrap();
+        (
+            Self {
+                ranks: sample_ranks(&words),
+                words,
+                values: values.unwrap(),
+            },
+            source,
+        )
+    }
+
+    /// Serializes optional values into `dest`, where nothing is written if they are absent.
+    pub fn serialize_option(dest: &mut Vec<u8>, values: Option<&Self>) {
+        if let Some(values) = values {
+            values.serialize_into_vec(dest);
+        }
+    }
+
+    /// Deserializes optional values from `source` if they are `present`.
+    pub fn deserialize_option(present: bool, source: &[u8]) -> (Option<Self>, &[u8]) {
+        if present {
+            let (values, source) = Self::deserialize_from_slice(source);
+            (Some(values), source)
+        } else {
+            (None, source)
+        }
+    }
+
+    /// Returns the number of bytes to serialize optional values.
+    pub fn option_io_bytes(values: Option<&Self>) -> usize {
+        values.map_or(0, Self::io_bytes)
+    }
+
+    /// Returns the number of bytes of heap used by optional values.
+    pub fn option_heap_bytes(values: Option<&Self>) -> usize {
+        values.map_or(0, Self::heap_bytes)
+    }
+}
+
+/// Returns the number of ones before each word.
+fn sample_ranks(words: &[u64]) -> Vec<u32> {
+    let mut ranks = Vec::with_capacity(words.len());
+    let mut rank = 0;
+    for &word in words {
+        ranks.push(rank);
+        rank += word.count_ones();
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let entries = [(0, 10), (63, 20), (64, 30), (100, 40), (199, 50)];
+        let values = SparseValues::new(200, entries.iter().copied());
+        for idx in 0..200 {
+            let expected = entries.iter().find(|e| e.0 == idx).map(|e| e.1);
+            assert_eq!(values.get(u32::try_from(idx).unwrap()), expected);
+        }
+        assert_eq!(values.get(200), None);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let values = SparseValues::new(130, [(1, 10), (65, 20), (129, 30)]);
+        let mut bytes = vec![];
+        values.serialize_into_vec(&mut bytes);
+        assert_eq!(values.io_bytes(), bytes.len());
+        let (other, remain) = SparseValues::deserialize_from_slice(&bytes);
+        assert!(remain.is_empty());
+        assert_eq!(values, other);
+    }
+}
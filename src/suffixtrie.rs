@@ -0,0 +1,363 @@
+//! A suffix trie form that stores keys reversed for right-to-left text processing.
+use crate::errors::Result;
+use crate::trie::Trie;
+
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+
+/// A suffix trie form that stores keys reversed for right-to-left text processing.
+///
+/// Keys and haystacks are given in the original order, and the trie reverses them internally.
+/// Positions are reported as offsets in characters from the beginning of the haystack.
+pub struct SuffixTrie {
+    trie: Trie,
+}
+
+impl SuffixTrie {
+    /// Creates a new [`SuffixTrie`] from input keys.
+    ///
+    /// Values in `[0..n-1]` will be associated with keys in the input order,
+    /// where `n` is the number of keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of string keys.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - `keys` is empty,
+    /// - `keys` contains empty strings,
+    /// - `keys` contains duplicate keys,
+    /// - the scale of `keys` exceeds the expected one, or
+    /// - the scale of the resulting trie exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = SuffixTrie::from_keys(keys).unwrap();
+    ///
+    /// assert_eq!(trie.num_elems(), 8);
+    /// ```
+    pub fn from_keys<I, K>(keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        Self::from_records(
+            keys.into_iter()
+                .enumerate()
+                .map(|(i, k)| (k, i.try_into().unwrap())),
+        )
+    }
+
+    /// Creates a new [`SuffixTrie`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// [`CrawdadError`](crate::errors::CrawdadError) will be returned when
+    ///
+    /// - `records` is empty,
+    /// - `records` contains empty strings,
+    /// - `records` contains duplicate keys,
+    /// - the scale of `keys` exceeds the expected one, or
+    /// - the scale of the resulting trie exceeds the expected one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let records = vec![("世界", 2), ("世界中", 3), ("国民", 2)];
+    /// let trie = SuffixTrie::from_records(records).unwrap();
+    ///
+    /// assert_eq!(trie.num_elems(), 8);
+    /// ```
+    pub fn from_records<I, K>(records: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, u32)>,
+        K: AsRef<str>,
+    {
+        let records = records
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().chars().rev().collect::<String>(), v));
+        let trie = Trie::from_records(records)?;
+        Ok(Self { trie })
+    }
+
+    /// Serializes the data structure into a [`Vec`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = SuffixTrie::from_keys(&keys).unwrap();
+    /// let bytes = trie.serialize_to_vec();
+    /// ```
+    pub fn serialize_to_vec(&self) -> Vec<u8> {
+        self.trie.serialize_to_vec()
+    }
+
+    /// Deserializes the data structure from a given byte slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A source byte slice.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of the data structure and the slice not used for the deserialization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = SuffixTrie::from_keys(&keys).unwrap();
+    ///
+    /// let bytes = trie.serialize_to_vec();
+    /// let (other, _) = SuffixTrie::deserialize_from_slice(&bytes);
+    ///
+    /// assert_eq!(trie.io_bytes(), other.io_bytes());
+    /// ```
+    pub fn deserialize_from_slice(source: &[u8]) -> (Self, &[u8]) {
+        let (trie, source) = Trie::deserialize_from_slice(source);
+        (Self { trie }, source)
+    }
+
+    /// Returns a value associated with an input key if exists.
+    ///
+    /// # Arguments
+    ///
+    /// - `key`: Search key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "国民"];
+    /// let trie = SuffixTrie::from_keys(&keys).unwrap();
+    ///
+    /// assert_eq!(trie.exact_match("世界中".chars()), Some(1));
+    /// assert_eq!(trie.exact_match("日本中".chars()), None);
+    /// ```
+    #[inline(always)]
+    pub fn exact_match<I>(&self, key: I) -> Option<u32>
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        self.trie.exact_match(key.into_iter().rev())
+    }
+
+    /// Returns an iterator for common suffix search.
+    ///
+    /// The iterator reports all occurrences of keys ending at the end of an input haystack,
+    /// where an occurrence consists of its associated value and starting position in characters.
+    /// Occurrences are reported in the descending order of their starting positions.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "中", "国民"];
+    /// let trie = SuffixTrie::from_keys(&keys).unwrap();
+    ///
+    /// let results: Vec<_> = trie.common_suffix_search("国民が世界中".chars()).collect();
+    ///
+    /// assert_eq!(results, vec![(2, 5), (1, 3)]);
+    /// ```
+    pub fn common_suffix_search<I>(&self, haystack: I) -> CommonSuffixSearchIter
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        let mut haystack = haystack.into_iter().rev();
+        // The length of the haystack is counted while scanning it from the end.
+        let mut num_chars = 0;
+        let mut matches: Vec<_> = self
+            .trie
+            .common_prefix_search(haystack.by_ref().inspect(|_| num_chars += 1))
+            .collect();
+        let len = num_chars + haystack.count();
+        for (_, pos) in &mut matches {
+            *pos = len - *pos;
+        }
+        CommonSuffixSearchIter {
+            inner: matches.into_iter(),
+        }
+    }
+
+    /// Returns the longest key that is a suffix of an input haystack.
+    ///
+    /// The result consists of the value associated with the key and its starting position
+    /// in characters. It is useful for backward maximum matching.
+    ///
+    /// # Arguments
+    ///
+    /// - `haystack`: Search haystack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crawdad::SuffixTrie;
+    ///
+    /// let keys = vec!["世界", "世界中", "中", "国民", "が"];
+    /// let trie = SuffixTrie::from_keys(&keys).unwrap();
+    ///
+    /// // Backward maximum matching
+    /// let mut haystack = "国民が世界中";
+    /// let mut words = vec![];
+    /// while let Some((_, start)) = trie.longest_suffix_match(haystack.chars()) {
+    ///     let (pos, _) = haystack.char_indices().nth(start).unwrap();
+    ///     words.push(&haystack[pos..]);
+    ///     haystack = &haystack[..pos];
+    /// }
+    ///
+    /// assert_eq!(words, vec!["世界中", "が", "国民"]);
+    /// ```
+    pub fn longest_suffix_match<I>(&self, haystack: I) -> Option<(u32, usize)>
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        let mut haystack = haystack.into_iter().rev();
+        // The length of the haystack is counted while scanning it from the end.
+        let mut num_chars = 0;
+        let result = self
+            .trie
+            .longest_prefix_match(haystack.by_ref().inspect(|_| num_chars += 1));
+        let len = num_chars + haystack.count();
+        result.map(|(value, match_len)| (value, len - match_len))
+    }
+
+    /// Returns the total amount of heap used by this automaton in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.trie.heap_bytes()
+    }
+
+    /// Returns the total amount of bytes to serialize the data structure.
+    pub fn io_bytes(&self) -> usize {
+        self.trie.io_bytes()
+    }
+
+    /// Returns the number of reserved elements.
+    pub fn num_elems(&self) -> usize {
+        self.trie.num_elems()
+    }
+
+    /// Returns the number of vacant elements.
+    ///
+    /// # Note
+    ///
+    /// It takes `O(num_elems)` time.
+    pub fn num_vacants(&self) -> usize {
+        self.trie.num_vacants()
+    }
+}
+
+/// Iterator for common suffix search.
+///
+/// The occurrences are found in advance because their starting positions depend on
+/// the length of the haystack.
+pub struct CommonSuffixSearchIter {
+    inner: vec::IntoIter<(u32, usize)>,
+}
+
+impl Iterator for CommonSuffixSearchIter {
+    type Item = (u32, usize);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_suffix_search() {
+        let keys = vec![
+            "世界",
+            "世界中",
+            "界中",
+            "中",
+            "国民",
+            "民",
+            "世論調査",
+            "調査",
+        ];
+        let trie = SuffixTrie::from_keys(&keys).unwrap();
+
+        let haystack: Vec<char> = "国民が世界中で世論調査".chars().collect();
+        for end in 0..=haystack.len() {
+            let text: String = haystack[..end].iter().collect();
+            let mut expected = vec![];
+            for start in (0..end).rev() {
+                let sub: String = haystack[start..end].iter().collect();
+                if let Some(i) = keys.iter().position(|&k| k == sub) {
+                    expected.push((u32::try_from(i).unwrap(), start));
+                }
+            }
+            let results: Vec<_> = trie.common_suffix_search(text.chars()).collect();
+            assert_eq!(results, expected);
+            assert_eq!(
+                trie.longest_suffix_match(text.chars()),
+                expected.last().copied()
+            );
+            assert_eq!(
+                trie.longest_suffix_match(haystack[..end].iter().copied()),
+                expected.last().copied()
+            );
+        }
+    }
+
+    #[test]
+    fn test_exact_match() {
+        let keys = vec!["世界", "世界中", "界中", "国民"];
+        let trie = SuffixTrie::from_keys(&keys).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(
+                trie.exact_match(key.chars()),
+                Some(u32::try_from(i).unwrap())
+            );
+        }
+        assert_eq!(trie.exact_match("中".chars()), None);
+        assert_eq!(trie.exact_match("中界世".chars()), None);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let keys = vec!["世界", "世界中", "界中", "国民"];
+        let trie = SuffixTrie::from_keys(&keys).unwrap();
+        let bytes = trie.serialize_to_vec();
+        assert_eq!(trie.io_bytes(), bytes.len());
+        let (other, remain) = SuffixTrie::deserialize_from_slice(&bytes);
+        assert!(remain.is_empty());
+        for key in &keys {
+            assert_eq!(
+                trie.exact_match(key.chars()),
+                other.exact_match(key.chars())
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use core::cmp::Ordering;
+use core::mem::size_of;
 
 use alloc::vec::Vec;
 
@@ -30,6 +31,228 @@ pub fn unpack_u32(slice: &[u8], nbytes: u8) -> u32 {
     u32::from_le_bytes(n_array)
 }
 
+/// Hints the processor to fetch the cache line containing `data[idx]` if it exists.
+///
+/// It is a no-op on architectures other than x86-64, because `core::arch::aarch64::_prefetch`
+/// is unstable and the inline assembly requires a newer compiler than the MSRV.
+#[inline(always)]
+pub fn prefetch<T>(data: &[T], idx: usize) {
+    #[cfg(target_arch = "x86_64")]
+    if let Some(x) = data.get(idx) {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        // SAFETY: Prefetching does not affect the semantics of the program,
+        // and the pointer is valid anyway.
+        unsafe { _mm_prefetch::<_MM_HINT_T0>((x as *const T).cast::<i8>()) };
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = (data, idx);
+}
+
+/// Fixed-size integer serialized in the little endian.
+pub trait Scalar: Copy {
+    /// The number of bytes to serialize a value.
+    const IO_BYTES: usize;
+
+    fn serialize_into_vec(self, dest: &mut Vec<u8>);
+
+    fn deserialize_from_slice(source: &[u8]) -> Self;
+}
+
+macro_rules! impl_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                const IO_BYTES: usize = size_of::<$t>();
+
+                #[inline(always)]
+                fn serialize_into_vec(self, dest: &mut Vec<u8>) {
+                    dest.extend_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline(always)]
+                fn deserialize_from_slice(source: &[u8]) -> Self {
+                    Self::from_le_bytes(source[..Self::IO_BYTES].try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(u8, u32, u64);
+
+/// Serializes an optional vector into `dest`, where nothing is written if it is absent.
+pub fn serialize_vec_option<T>(dest: &mut Vec<u8>, vec: Option<&Vec<T>>)
+where
+    T: Scalar,
+{
+    if let Some(vec) = vec {
+        dest.extend_from_slice(&u32::try_from(vec.len()).unwrap().to_le_bytes());
+        for &x in vec {
+            x.serialize_into_vec(dest);
+        }
+    }
+}
+
+/// Deserializes an optional vector from `source` if it is `present`.
+pub fn deserialize_vec_option<T>(present: bool, mut source: &[u8]) -> (Option<Vec<T>>, &[u8])
+where
+    T: Scalar,
+{
+    if !present {
+        return (None, source);
+    }
+    let len = u32::from_le_bytes(source[..4].try_into().unwrap()) as usize;
+    source = &source[4..];
+    let mut vec = Vec::with_capacity(len);
+    for _ in 0..len {
+        vec.push(T::deserialize_from_slice(source));
+        source = &source[T::IO_BYTES..];
+    }
+    (Some(vec), source)
+}
+
+/// Returns the number of bytes to serialize an optional vector.
+pub fn vec_option_io_bytes<T>(vec: Option<&Vec<T>>) -> usize
+where
+    T: Scalar,
+{
+    vec.map_or(0, |vec| T::IO_BYTES * vec.len() + size_of::<u32>())
+}
+
+/// Returns the number of bytes of heap used by an optional vector.
+pub fn vec_option_heap_bytes<T>(vec: Option<&Vec<T>>) -> usize {
+    vec.map_or(0, |vec| vec.len() * size_of::<T>())
+}
+
+/// Computes the next row `cur` of the dynamic programming table for the Levenshtein distance
+/// between `query` and a string extended by `x` from the previous row `prev`,
+/// and returns the minimum value in `cur`.
+#[inline(always)]
+pub fn levenshtein_step<T>(query: &[T], prev: &[usize], cur: &mut [usize], x: &T) -> usize
+where
+    T: PartialEq,
+{
+    debug_assert_eq!(prev.len(), query.len() + 1);
+    debug_assert_eq!(cur.len(), query.len() + 1);
+    cur[0] = prev[0] + 1;
+    let mut min = cur[0];
+    for j in 1..cur.len() {
+        let cost = usize::from(query[j - 1] != *x);
+        cur[j] = (prev[j - 1] + cost).min(prev[j] + 1).min(cur[j - 1] + 1);
+        min = min.min(cur[j]);
+    }
+    min
+}
+
+/// Token of a wildcard pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WildcardToken<T> {
+    /// Matches the symbol.
+    Literal(T),
+    /// Matches any single character.
+    Any,
+    /// Matches any sequence of characters.
+    AnySeq,
+}
+
+impl<T> WildcardToken<T> {
+    /// Maps the symbol of a literal with `f`.
+    #[inline(always)]
+    pub fn map<U, F>(self, f: F) -> WildcardToken<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Self::Literal(x) => WildcardToken::Literal(f(x)),
+            Self::Any => WildcardToken::Any,
+            Self::AnySeq => WildcardToken::AnySeq,
+        }
+    }
+}
+
+/// Parses a wildcard pattern, where `?` matches any single character, `*` matches any sequence
+/// of characters, and `\` makes the following character a literal.
+pub fn parse_wildcard<I>(pattern: I) -> Vec<WildcardToken<char>>
+where
+    I: IntoIterator<Item = char>,
+{
+    let mut pattern = pattern.into_iter();
+    let mut tokens = vec![];
+    while let Some(c) = pattern.next() {
+        tokens.push(match c {
+            '?' => WildcardToken::Any,
+            '*' => WildcardToken::AnySeq,
+            // A trailing backslash matches itself.
+            '\\' => WildcardToken::Literal(pattern.next().unwrap_or('\\')),
+            c => WildcardToken::Literal(c),
+        });
+    }
+    tokens
+}
+
+/// Removes consecutive repeated elements in `vec[start..]`, keeping `vec[..start]` untouched.
+pub fn dedup_from<T>(vec: &mut Vec<T>, start: usize)
+where
+    T: PartialEq,
+{
+    let mut len = start;
+    for i in start..vec.len() {
+        if len == start || vec[len - 1] != vec[i] {
+            vec.swap(len, i);
+            len += 1;
+        }
+    }
+    vec.truncate(len);
+}
+
+/// Computes the set of pattern positions `cur` reached from `prev` by consuming `x`,
+/// and returns `false` if the set is empty.
+#[inline(always)]
+pub fn wildcard_step<T>(
+    pattern: &[WildcardToken<T>],
+    prev: &[bool],
+    cur: &mut [bool],
+    x: &T,
+) -> bool
+where
+    T: PartialEq,
+{
+    debug_assert_eq!(prev.len(), pattern.len() + 1);
+    debug_assert_eq!(cur.len(), pattern.len() + 1);
+    cur.iter_mut().for_each(|x| *x = false);
+    for (j, token) in pattern.iter().enumerate() {
+        if !prev[j] {
+            continue;
+        }
+        match token {
+            WildcardToken::Literal(y) => {
+                if y == x {
+                    cur[j + 1] = true;
+                }
+            }
+            WildcardToken::Any => cur[j + 1] = true,
+            WildcardToken::AnySeq => cur[j] = true,
+        }
+    }
+    wildcard_closure(pattern, cur)
+}
+
+/// Adds the pattern positions reached without consuming characters to `row`,
+/// and returns `false` if the set is empty.
+#[inline(always)]
+pub fn wildcard_closure<T>(pattern: &[WildcardToken<T>], row: &mut [bool]) -> bool {
+    let mut exists = false;
+    for j in 0..row.len() {
+        if row[j] {
+            exists = true;
+            if matches!(pattern.get(j), Some(WildcardToken::AnySeq)) {
+                row[j + 1] = true;
+            }
+        }
+    }
+    exists
+}
+
 /// Returns `(lcp, ord)` such that
 ///  - lcp: Length of longest commom prefix of `a` and `b`.
 ///  - ord: `Ordering` between `a` and `b`.
@@ -48,6 +271,67 @@ pub fn longest_common_prefix(a: &[char], b: &[char]) -> (usize, Ordering) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_levenshtein_step() {
+        let query: Vec<_> = "kitten".chars().collect();
+        let mut prev: Vec<_> = (0..=query.len()).collect();
+        let mut cur = vec![0; query.len() + 1];
+        for c in "sitting".chars() {
+            levenshtein_step(&query, &prev, &mut cur, &c);
+            core::mem::swap(&mut prev, &mut cur);
+        }
+        assert_eq!(prev[query.len()], 3);
+    }
+
+    #[test]
+    fn test_wildcard_step() {
+        let pattern = [
+            WildcardToken::AnySeq,
+            WildcardToken::Literal(Some(0)),
+            WildcardToken::Any,
+            WildcardToken::AnySeq,
+        ];
+        let mut prev = vec![true, false, false, false, false];
+        wildcard_closure(&pattern, &mut prev);
+        assert_eq!(prev, vec![true, true, false, false, false]);
+        let mut cur = vec![false; 5];
+        assert!(wildcard_step(&pattern, &prev, &mut cur, &Some(0)));
+        assert_eq!(cur, vec![true, true, true, false, false]);
+        core::mem::swap(&mut prev, &mut cur);
+        assert!(wildcard_step(&pattern, &prev, &mut cur, &Some(1)));
+        assert_eq!(cur, vec![true, true, false, true, true]);
+        let pattern = [WildcardToken::Literal(Some(0))];
+        assert!(!wildcard_step(
+            &pattern,
+            &[true, false],
+            &mut [false; 2],
+            &Some(1)
+        ));
+    }
+
+    #[test]
+    fn test_parse_wildcard() {
+        assert_eq!(
+            parse_wildcard(r"a?\?*\*\\\".chars()),
+            vec![
+                WildcardToken::Literal('a'),
+                WildcardToken::Any,
+                WildcardToken::Literal('?'),
+                WildcardToken::AnySeq,
+                WildcardToken::Literal('*'),
+                WildcardToken::Literal('\\'),
+                WildcardToken::Literal('\\'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_from() {
+        let mut vec = vec![1, 1, 2, 2, 2, 3, 1, 1];
+        dedup_from(&mut vec, 1);
+        assert_eq!(vec, vec![1, 1, 2, 3, 1]);
+    }
+
     #[test]
     fn test_longest_common_prefix() {
         assert_eq!(